@@ -5,40 +5,113 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, AttributeArgs, Item, Meta, NestedMeta};
+use syn::{parse_macro_input, AttributeArgs, DeriveInput, Item, Lit, Meta, NestedMeta};
 
 #[proc_macro_attribute]
 pub fn tagged(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as AttributeArgs);
     let input = parse_macro_input!(input as Item);
+    expand_tagged(args, input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+const UNKNOWN_MARK: &str = "Unknown tagged argument, should be \"compressed\", \"checked\", \"canonical_bytes\", \"hash_by_bytes\", \"no_serde\", \"const_tag\", \"len = N\" or \"delim = C\".";
+
+fn expand_tagged(args: AttributeArgs, input: Item) -> syn::Result<proc_macro2::TokenStream> {
     let (name, generics) = match &input {
         Item::Struct(item) => (&item.ident, &item.generics),
         Item::Enum(item) => (&item.ident, &item.generics),
-        _ => panic!("expected struct or enum"),
+        item => {
+            return Err(syn::Error::new_spanned(
+                item,
+                "`tagged` can only be applied to a struct or enum",
+            ))
+        }
     };
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let mut compressed = false;
     let mut checked = false;
-    let (tag, marks): (&dyn quote::ToTokens, _) = match args.as_slice() {
-        [NestedMeta::Lit(tag), marks @ ..] => (tag, marks),
-        [NestedMeta::Meta(Meta::Path(path)), marks @ ..] => (path, marks),
-        x => panic!(
-            "`tagged` takes at least one argument, the tag, as a string literal or expression, found {:?}",
-            x
-        ),
-    };
-    marks.iter().for_each(|attr| match attr {
-        NestedMeta::Meta(Meta::Path(path)) => {
-            if path.is_ident("compressed") {
-                compressed = true;
-            } else if path.is_ident("checked") {
-                checked = true;
-            } else {
-                panic!("Unkown tagged argument, should be either \"compressed\" or \"checked\".")
+    let mut canonical_bytes = false;
+    let mut hash_by_bytes = false;
+    let mut no_serde = false;
+    let mut const_tag = false;
+    let mut len: Option<usize> = None;
+    let mut delim: Option<char> = None;
+    let (tag, marks): (&dyn quote::ToTokens, _) =
+        match args.as_slice() {
+            [NestedMeta::Lit(tag), marks @ ..] => (tag, marks),
+            [NestedMeta::Meta(Meta::Path(path)), marks @ ..] => (path, marks),
+            [arg, ..] => return Err(syn::Error::new_spanned(
+                arg,
+                "`tagged` takes at least one argument, the tag, as a string literal or expression",
+            )),
+            [] => return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                "`tagged` takes at least one argument, the tag, as a string literal or expression",
+            )),
+        };
+    for attr in marks {
+        match attr {
+            NestedMeta::Meta(Meta::Path(path)) => {
+                if path.is_ident("compressed") {
+                    compressed = true;
+                } else if path.is_ident("checked") {
+                    checked = true;
+                } else if path.is_ident("canonical_bytes") {
+                    canonical_bytes = true;
+                } else if path.is_ident("hash_by_bytes") {
+                    hash_by_bytes = true;
+                } else if path.is_ident("no_serde") {
+                    no_serde = true;
+                } else if path.is_ident("const_tag") {
+                    const_tag = true;
+                } else {
+                    return Err(syn::Error::new_spanned(path, UNKNOWN_MARK));
+                }
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("delim") => {
+                let c = match &nv.lit {
+                    Lit::Char(c) => c.value(),
+                    Lit::Str(s) if s.value().chars().count() == 1 => {
+                        s.value().chars().next().unwrap()
+                    }
+                    lit => {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "`delim` must be a single character",
+                        ))
+                    }
+                };
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    return Err(syn::Error::new_spanned(
+                        &nv.lit,
+                        "`delim` must not be a base64 character",
+                    ));
+                }
+                delim = Some(c);
             }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("len") => match &nv.lit {
+                Lit::Int(n) => {
+                    len = Some(
+                        n.base10_parse()
+                            .map_err(|_| syn::Error::new_spanned(n, "`len` must be a usize"))?,
+                    )
+                }
+                lit => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "`len` must be an integer literal",
+                    ))
+                }
+            },
+            attr => return Err(syn::Error::new_spanned(attr, UNKNOWN_MARK)),
         }
-        _ => panic!("Unkown tagged argument, should be either \"compressed\" or \"checked\"."),
-    });
+    }
+    let expected_len = match len {
+        Some(n) => quote!(Some(#n)),
+        None => quote!(None),
+    };
     let serialize_token = if compressed {
         quote!(serialize_compressed)
     } else {
@@ -56,25 +129,132 @@ pub fn tagged(args: TokenStream, input: TokenStream) -> TokenStream {
         quote!(deserialize_uncompressed_unchecked)
     };
 
-    #[cfg(feature = "serde")]
-    let struct_def = quote! {
-        #[derive(serde::Serialize, serde::Deserialize)]
-        #[serde(try_from = "tagged_base64::TaggedBase64", into = "tagged_base64::TaggedBase64")]
-        // Override the inferred bound for Serialize/Deserialize impls. If we're converting to and
-        // from CanonicalBytes as an intermediate, the impls should work for any generic parameters.
-        #[serde(bound = "")]
-        #input
+    let display_from_str = match delim {
+        None => quote! {
+            impl #impl_generics ark_std::fmt::Display for #name #ty_generics #where_clause {
+                fn fmt(&self, f: &mut ark_std::fmt::Formatter<'_>) -> ark_std::fmt::Result {
+                    ark_std::write!(
+                        f, "{}",
+                        tagged_base64::TaggedBase64::from(self)
+                    )
+                }
+            }
+
+            impl #impl_generics ark_std::str::FromStr for #name #ty_generics #where_clause {
+                type Err = tagged_base64::Tb64Error;
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    use core::convert::TryFrom;
+                    Self::try_from(tagged_base64::TaggedBase64::from_str(s)?)
+                        .map_err(|_| tagged_base64::Tb64Error::InvalidData)
+                }
+            }
+        },
+        Some(delim) => quote! {
+            impl #impl_generics ark_std::fmt::Display for #name #ty_generics #where_clause {
+                fn fmt(&self, f: &mut ark_std::fmt::Formatter<'_>) -> ark_std::fmt::Result {
+                    let s = tagged_base64::TaggedBase64::from(self)
+                        .to_string_with_delim(#delim)
+                        .map_err(|_| ark_std::fmt::Error)?;
+                    f.write_str(&s)
+                }
+            }
+
+            impl #impl_generics ark_std::str::FromStr for #name #ty_generics #where_clause {
+                type Err = tagged_base64::Tb64Error;
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    use core::convert::TryFrom;
+                    Self::try_from(tagged_base64::TaggedBase64::parse_with_delim(s, #delim)?)
+                        .map_err(|_| tagged_base64::Tb64Error::InvalidData)
+                }
+            }
+        },
+    };
+
+    let canonical_bytes_def = if canonical_bytes {
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// The canonical serialization of this value, which is the value of its tagged
+                /// base 64 representation.
+                pub fn canonical_bytes(&self) -> ark_std::vec::Vec<u8> {
+                    let mut bytes = ark_std::vec![];
+                    CanonicalSerialize::#serialize_token(self, &mut bytes).unwrap();
+                    bytes
+                }
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    let hash_by_bytes_def = if hash_by_bytes {
+        quote! {
+            impl #impl_generics core::hash::Hash for #name #ty_generics #where_clause {
+                fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                    let mut bytes = ark_std::vec![];
+                    CanonicalSerialize::#serialize_token(self, &mut bytes).unwrap();
+                    core::hash::Hash::hash(&bytes, state);
+                }
+            }
+
+            impl #impl_generics core::cmp::PartialEq for #name #ty_generics #where_clause {
+                fn eq(&self, other: &Self) -> bool {
+                    let mut bytes = ark_std::vec![];
+                    CanonicalSerialize::#serialize_token(self, &mut bytes).unwrap();
+                    let mut other_bytes = ark_std::vec![];
+                    CanonicalSerialize::#serialize_token(other, &mut other_bytes).unwrap();
+                    bytes == other_bytes
+                }
+            }
+
+            impl #impl_generics core::cmp::Eq for #name #ty_generics #where_clause {}
+        }
+    } else {
+        quote!()
+    };
+
+    // The constant is opt-in, since an inherent `TAG` would clash with one the type already has.
+    let const_tag_def = if const_tag {
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// The tag, usable in `const` contexts such as `match` patterns.
+                pub const TAG: &'static str = #tag;
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    // The `no_serde` mark lets users write their own serde impls without conflicting derives.
+    let struct_def = if cfg!(feature = "serde") && !no_serde {
+        quote! {
+            #[derive(serde::Serialize, serde::Deserialize)]
+            #[serde(try_from = "tagged_base64::TaggedBase64", into = "tagged_base64::TaggedBase64")]
+            // Override the inferred bound for Serialize/Deserialize impls. If we're converting to and
+            // from CanonicalBytes as an intermediate, the impls should work for any generic parameters.
+            #[serde(bound = "")]
+            #input
+        }
+    } else {
+        quote!(#input)
     };
-    #[cfg(not(feature = "serde"))]
-    let struct_def = &input;
 
     let output = quote! {
         #struct_def
 
+        #canonical_bytes_def
+
+        #hash_by_bytes_def
+
+        #const_tag_def
+
         impl #impl_generics tagged_base64::Tagged for #name #ty_generics #where_clause {
             fn tag() -> ark_std::string::String {
                 ark_std::string::String::from(#tag)
             }
+
+            fn expected_len() -> Option<usize> {
+                #expected_len
+            }
         }
 
         impl #impl_generics core::convert::TryFrom<tagged_base64::TaggedBase64>
@@ -94,11 +274,18 @@ pub fn tagged(args: TokenStream, input: TokenStream) -> TokenStream {
         {
             type Error = tagged_base64::Tb64Error;
             fn try_from(t: &tagged_base64::TaggedBase64) -> Result<Self, Self::Error> {
-                if t.tag() == <#name #ty_generics as tagged_base64::Tagged>::tag() {
-                    <Self as CanonicalDeserialize>::#deserialize_token(t.as_ref())
-                        .map_err(|_| tagged_base64::Tb64Error::InvalidData)
-                } else {
-                    Err(tagged_base64::Tb64Error::InvalidTag)
+                if t.tag() != <#name #ty_generics as tagged_base64::Tagged>::tag() {
+                    return Err(tagged_base64::Tb64Error::UnexpectedTag);
+                }
+                // A value of just the checksum usually means an uninitialized field was
+                // serialized, so report it clearly rather than as a deserialization failure.
+                let bytes: &[u8] = t.as_ref();
+                match <#name #ty_generics as tagged_base64::Tagged>::expected_len() {
+                    Some(expected) if expected != 0 && bytes.is_empty() => {
+                        Err(tagged_base64::Tb64Error::WrongLength { expected, actual: 0 })
+                    }
+                    _ => <Self as CanonicalDeserialize>::#deserialize_token(bytes)
+                        .map_err(|_| tagged_base64::Tb64Error::InvalidData),
                 }
             }
         }
@@ -121,23 +308,72 @@ pub fn tagged(args: TokenStream, input: TokenStream) -> TokenStream {
             }
         }
 
-        impl #impl_generics ark_std::fmt::Display for #name #ty_generics #where_clause {
-            fn fmt(&self, f: &mut ark_std::fmt::Formatter<'_>) -> ark_std::fmt::Result {
-                ark_std::write!(
-                    f, "{}",
-                    tagged_base64::TaggedBase64::from(self)
-                )
+        #display_from_str
+    };
+    Ok(output)
+}
+
+#[proc_macro_derive(Tagged, attributes(tagged_tag, tagged_const_tag))]
+pub fn derive_tagged(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_derive_tagged(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_derive_tagged(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attr = input
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("tagged_tag"))
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                name,
+                "`derive(Tagged)` requires a `#[tagged_tag = \"TAG\"]` attribute",
+            )
+        })?;
+    let tag = match attr.parse_meta() {
+        Ok(Meta::NameValue(nv)) => match nv.lit {
+            Lit::Str(tag) => tag,
+            lit => {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "`tagged_tag` must be a string literal",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "expected `#[tagged_tag = \"TAG\"]`",
+            ))
+        }
+    };
+    let const_tag_def = if input
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("tagged_const_tag"))
+    {
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// The tag, usable in `const` contexts such as `match` patterns.
+                pub const TAG: &'static str = #tag;
             }
         }
+    } else {
+        quote!()
+    };
 
-        impl #impl_generics ark_std::str::FromStr for #name #ty_generics #where_clause {
-            type Err = tagged_base64::Tb64Error;
-            fn from_str(s: &str) -> Result<Self, Self::Err> {
-                use core::convert::TryFrom;
-                Self::try_from(tagged_base64::TaggedBase64::from_str(s)?)
-                    .map_err(|_| tagged_base64::Tb64Error::InvalidData)
+    let output = quote! {
+        #const_tag_def
+
+        impl #impl_generics tagged_base64::Tagged for #name #ty_generics #where_clause {
+            fn tag() -> ark_std::string::String {
+                ark_std::string::String::from(#tag)
             }
         }
     };
-    output.into()
+    Ok(output)
 }
@@ -0,0 +1,50 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Compares [TaggedBase64::parse] against a bare base64 decode of the same
+//! value, to measure the overhead of splitting the tag and checking the
+//! checksum.
+//!
+//! Run with `cargo bench --bench parse`.
+
+use base64::Engine;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use tagged_base64::{TaggedBase64, BASE64};
+
+/// Runs `f` repeatedly for about a second and returns the mean time per
+/// call.
+fn time_per_iter(mut f: impl FnMut()) -> Duration {
+    // Warm up, and estimate how many iterations fit in the time budget.
+    let start = Instant::now();
+    let mut iters = 0u32;
+    while start.elapsed() < Duration::from_millis(100) {
+        f();
+        iters += 1;
+    }
+    let iters = iters * 10;
+
+    let start = Instant::now();
+    for _ in 0..iters {
+        f();
+    }
+    start.elapsed() / iters
+}
+
+fn main() {
+    for (name, len) in [("small", 32), ("large", 64 * 1024)] {
+        let value: Vec<u8> = (0..len).map(|i| (i * 7 + 3) as u8).collect();
+        let tb64 = TaggedBase64::new("BENCH", &value).unwrap().to_string();
+        let raw = TaggedBase64::encode_raw(&value);
+
+        let parse = time_per_iter(|| {
+            black_box(TaggedBase64::parse(black_box(&tb64)).unwrap());
+        });
+        let decode = time_per_iter(|| {
+            black_box(BASE64.decode(black_box(&raw)).unwrap());
+        });
+        println!(
+            "{name} ({len} bytes): parse {parse:?}, base64 decode {decode:?}, ratio {:.2}",
+            parse.as_secs_f64() / decode.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Framing of newline-delimited TaggedBase64 values for
+//! [tokio_util::codec], so that services can read and write streams of
+//! tagged values over a socket with `FramedRead` and `FramedWrite`.
+
+extern crate std;
+
+use crate::{TaggedBase64, Tb64Error};
+use bytes::{Buf, BytesMut};
+use core::fmt::Write;
+use snafu::Snafu;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Errors from reading or writing a stream of TaggedBase64 values.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum CodecError {
+    /// Reading from or writing to the underlying stream failed.
+    #[snafu(display("I/O error: {source}"))]
+    Io { source: std::io::Error },
+    /// A line was not valid UTF-8.
+    InvalidUtf8,
+    /// A line was not a valid TaggedBase64.
+    #[snafu(display("{source}"))]
+    Parse { source: Tb64Error },
+    /// A line was longer than the codec's maximum length.
+    ///
+    /// The rest of the line is discarded, and decoding resumes at the next
+    /// line.
+    #[snafu(display("line exceeds the maximum length of {max_length} bytes"))]
+    LineTooLong { max_length: usize },
+}
+
+impl From<std::io::Error> for CodecError {
+    fn from(source: std::io::Error) -> Self {
+        Self::Io { source }
+    }
+}
+
+impl From<Tb64Error> for CodecError {
+    fn from(source: Tb64Error) -> Self {
+        Self::Parse { source }
+    }
+}
+
+/// A codec which frames TaggedBase64 values one per line.
+///
+/// Lines may end with `\n` or `\r\n`. A final line without a line ending is
+/// decoded at the end of the stream.
+///
+/// A codec from [new](Self::new) buffers lines of any length, so a peer
+/// which never sends a newline can exhaust memory. Use
+/// [new_with_max_length](Self::new_with_max_length) when reading from an
+/// untrusted source.
+#[derive(Clone, Debug)]
+pub struct TaggedBase64Codec {
+    /// How much of the buffer has already been searched for a newline, so
+    /// that partial lines are not rescanned on every read.
+    next_index: usize,
+    /// The longest line accepted, not counting the line ending.
+    max_length: usize,
+    /// Whether the rest of an overlong line is being skipped.
+    is_discarding: bool,
+}
+
+impl Default for TaggedBase64Codec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaggedBase64Codec {
+    /// Constructs a codec with no limit on the length of a line.
+    pub fn new() -> Self {
+        Self::new_with_max_length(usize::MAX)
+    }
+
+    /// Constructs a codec which rejects lines longer than `max_length`
+    /// bytes, not counting the line ending.
+    ///
+    /// An overlong line is reported as [CodecError::LineTooLong] as soon as
+    /// it is detected, without buffering the rest of it. The remainder of
+    /// the line is then discarded, and the next call decodes the following
+    /// line.
+    pub fn new_with_max_length(max_length: usize) -> Self {
+        Self {
+            next_index: 0,
+            max_length,
+            is_discarding: false,
+        }
+    }
+
+    /// The longest line this codec accepts, not counting the line ending.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    fn parse_line(&self, line: &[u8]) -> Result<TaggedBase64, CodecError> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.len() > self.max_length {
+            return Err(CodecError::LineTooLong {
+                max_length: self.max_length,
+            });
+        }
+        let line = core::str::from_utf8(line).map_err(|_| CodecError::InvalidUtf8)?;
+        Ok(TaggedBase64::parse(line)?)
+    }
+}
+
+impl Decoder for TaggedBase64Codec {
+    type Item = TaggedBase64;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<TaggedBase64>, CodecError> {
+        loop {
+            // Allow one byte beyond the maximum for the newline, and for a
+            // carriage return before it.
+            let read_to = self.max_length.saturating_add(2).min(src.len());
+            let newline = src[self.next_index..read_to]
+                .iter()
+                .position(|b| *b == b'\n');
+            match (self.is_discarding, newline) {
+                (true, Some(offset)) => {
+                    // Skip the end of an overlong line and look for the next.
+                    src.advance(self.next_index + offset + 1);
+                    self.next_index = 0;
+                    self.is_discarding = false;
+                }
+                (true, None) => {
+                    src.advance(read_to);
+                    self.next_index = 0;
+                    if src.is_empty() {
+                        return Ok(None);
+                    }
+                }
+                (false, Some(offset)) => {
+                    let line = src.split_to(self.next_index + offset + 1);
+                    self.next_index = 0;
+                    return self.parse_line(&line[..line.len() - 1]).map(Some);
+                }
+                (false, None) if src.len() > self.max_length.saturating_add(1) => {
+                    // The line is too long whatever follows. Report it now
+                    // rather than buffering the rest of it.
+                    self.is_discarding = true;
+                    return Err(CodecError::LineTooLong {
+                        max_length: self.max_length,
+                    });
+                }
+                (false, None) => {
+                    self.next_index = read_to;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<TaggedBase64>, CodecError> {
+        match self.decode(src)? {
+            Some(tb64) => Ok(Some(tb64)),
+            None if src.is_empty() => Ok(None),
+            None => {
+                let line = src.split();
+                self.next_index = 0;
+                if self.is_discarding {
+                    self.is_discarding = false;
+                    return Ok(None);
+                }
+                self.parse_line(&line).map(Some)
+            }
+        }
+    }
+}
+
+impl Encoder<&TaggedBase64> for TaggedBase64Codec {
+    type Error = CodecError;
+
+    fn encode(&mut self, tb64: &TaggedBase64, dst: &mut BytesMut) -> Result<(), CodecError> {
+        dst.reserve(tb64.encoded_len() + 1);
+        writeln!(dst, "{}", tb64).expect("writing to a BytesMut does not fail");
+        Ok(())
+    }
+}
+
+impl Encoder<TaggedBase64> for TaggedBase64Codec {
+    type Error = CodecError;
+
+    fn encode(&mut self, tb64: TaggedBase64, dst: &mut BytesMut) -> Result<(), CodecError> {
+        self.encode(&tb64, dst)
+    }
+}
@@ -0,0 +1,56 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Borrowed access to the tag and value of a TaggedBase64 for hosts
+//! other than Rust and JavaScript.
+//!
+//! [TaggedBase64::tag_ptr_len] and [TaggedBase64::value_ptr_len] return a
+//! pointer and length into the TaggedBase64 without cloning. They are
+//! meant to be wrapped by `extern "C"` functions in the crate which embeds
+//! this one, for example
+//!
+//! ```ignore
+//! #[repr(C)]
+//! pub struct Bytes {
+//!     ptr: *const u8,
+//!     len: usize,
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn my_value(tb64: &TaggedBase64) -> Bytes {
+//!     let (ptr, len) = tb64.value_ptr_len();
+//!     Bytes { ptr, len }
+//! }
+//! ```
+//!
+//! A C header for those wrappers can then be generated with
+//! [cbindgen](https://github.com/mozilla/cbindgen), with `TaggedBase64`
+//! declared as an opaque type.
+//!
+//! # Safety
+//!
+//! The pointers borrow from the TaggedBase64. They are only valid while
+//! the TaggedBase64 is alive and not modified: dropping it, or calling a
+//! method such as [TaggedBase64::set_value] which replaces the tag or
+//! value, leaves them dangling. The bytes must not be written through the
+//! pointers. The tag is ASCII but is not NUL-terminated.
+
+use crate::TaggedBase64;
+
+impl TaggedBase64 {
+    /// Returns a pointer to the bytes of the tag and their length.
+    ///
+    /// See the [module documentation](crate::ffi) for the safety
+    /// invariants.
+    pub fn tag_ptr_len(&self) -> (*const u8, usize) {
+        let tag = self.tag_bytes();
+        (tag.as_ptr(), tag.len())
+    }
+
+    /// Returns a pointer to the bytes of the value and their length.
+    ///
+    /// See the [module documentation](crate::ffi) for the safety
+    /// invariants.
+    pub fn value_ptr_len(&self) -> (*const u8, usize) {
+        (self.value.as_ptr(), self.value.len())
+    }
+}
@@ -42,15 +42,23 @@
 
 #![no_std]
 #![allow(clippy::unused_unit)]
+extern crate alloc;
+
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
 #[cfg(feature = "ark-serialize")]
 use ark_serialize::*;
 use base64::{
     alphabet::URL_SAFE,
-    engine::{general_purpose::NO_PAD, Engine, GeneralPurpose},
+    engine::{
+        general_purpose::{NO_PAD, STANDARD},
+        Engine, GeneralPurpose,
+    },
 };
-use core::fmt;
 #[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
 use core::fmt::Display;
+use core::fmt::{self, Write as _};
+use core::ops::Deref;
 use core::str::FromStr;
 use crc_any::CRC;
 #[cfg(feature = "serde")]
@@ -61,6 +69,8 @@ use serde::{
 use snafu::Snafu;
 
 use ark_std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
     format,
     string::{String, ToString},
     vec::Vec,
@@ -69,6 +79,17 @@ use ark_std::{
 #[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
 use wasm_bindgen::prelude::*;
 
+#[cfg(feature = "tokio-codec")]
+pub mod codec;
+#[cfg(feature = "c-ffi")]
+pub mod ffi;
+#[cfg(feature = "qr")]
+pub mod qr;
+#[cfg(feature = "json-schema")]
+pub mod schema;
+#[cfg(feature = "std")]
+pub mod writer;
+
 /// Derive serdes for a type which serializes as a binary blob.
 ///
 /// This macro can be used to easily derive friendly serde implementations for a binary type which
@@ -85,6 +106,24 @@ use wasm_bindgen::prelude::*;
 ///   with `uncompressed` and `unchecked` flags.
 /// * If `compressed` and/or `checked` flags are presented, the derived implementation will behave
 ///   accordingly.
+/// * If the `canonical_bytes` flag is present, the type also gets an inherent
+///   `fn canonical_bytes(&self) -> Vec<u8>` method returning its serialization, which is the
+///   value of its tagged base 64 representation.
+/// * If the `hash_by_bytes` flag is present, the type gets implementations of `Hash`, `PartialEq`
+///   and `Eq` which hash and compare its serialization, so that it can be used as a map key even if
+///   the wrapped type does not implement them. Do not also derive these traits.
+/// * If the `no_serde` flag is present, the serde derives are not added, even with the `serde`
+///   feature enabled, so that the type can have hand-written serde implementations.
+/// * If the `const_tag` flag is present, the type also gets an associated constant
+///   `TAG: &'static str` holding the tag, which can be used at compile time, for example as a
+///   `match` pattern when dispatching on the tags of a closed set of types. It is opt-in so that it
+///   does not clash with a `TAG` the type already defines.
+/// * If `delim = "."` is present, the [Display](ark_std::fmt::Display) and
+///   [FromStr](ark_std::str::FromStr) implementations use that delimiter instead of `~`, via
+///   [TaggedBase64::to_string_with_delim] and [TaggedBase64::parse_with_delim]. Serialization is
+///   unaffected.
+/// * If `len = N` is present, [Tagged::expected_len] returns `Some(N)`, and a tagged base 64 value
+///   which is empty, holding only the checksum, is rejected with [Tb64Error::WrongLength].
 ///
 /// Specifically, this macro does 4 things when applied to a type definition:
 /// * It adds `#[derive(Serialize, Deserialize)]` to the type definition, along with serde
@@ -138,25 +177,197 @@ use wasm_bindgen::prelude::*;
 /// ```
 /// which will produce a tagged base64 string like
 /// "PRIM~8oaujwbov8h4eEq7HFpqW6mIXhVbtJGxLUgiKrGpMCoJ".
+///
+/// The `TAG` constant from the `const_tag` flag allows dispatching on the tag of a parsed value:
+/// ```
+/// # use ark_serialize::*;
+/// # use tagged_base64::TaggedBase64;
+/// # use tagged_base64_macros::tagged;
+/// #[tagged("PRIM", const_tag)]
+/// #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+/// struct CryptoPrim(ark_bls12_381::Fr);
+///
+/// #[tagged("SIG", const_tag)]
+/// #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+/// struct Signature(ark_bls12_381::Fr);
+///
+/// fn describe(tb64: &TaggedBase64) -> &'static str {
+///     match tb64.tag().as_str() {
+///         CryptoPrim::TAG => "a primitive",
+///         Signature::TAG => "a signature",
+///         _ => "unknown",
+///     }
+/// }
+///
+/// let sig = TaggedBase64::new("SIG", &[]).unwrap();
+/// assert_eq!(describe(&sig), "a signature");
+/// ```
+///
+/// The macro can only be applied to a struct or enum. On anything else, such as a union, it reports
+/// the error "`tagged` can only be applied to a struct or enum" at the item:
+/// ```compile_fail
+/// # use tagged_base64_macros::tagged;
+/// #[tagged("UNION")]
+/// union Bits {
+///     int: u32,
+///     float: f32,
+/// }
+/// ```
 pub use tagged_base64_macros::tagged;
 
+/// Derive [Tagged](trait@Tagged) with a tag given by a `tagged_tag` attribute.
+///
+/// Unlike the [macro@tagged] macro, this implements only the trait, for types which manage their
+/// own serialization. A `tagged_const_tag` attribute also adds an associated constant `TAG`, like
+/// the `const_tag` flag of [macro@tagged].
+///
+/// ```
+/// use tagged_base64::Tagged;
+///
+/// #[derive(Tagged)]
+/// #[tagged_tag = "FOO"]
+/// #[tagged_const_tag]
+/// struct Foo(u64);
+///
+/// assert_eq!(Foo::tag(), "FOO");
+/// assert_eq!(Foo::TAG, "FOO");
+/// ```
+pub use tagged_base64_macros::Tagged;
+
 /// Separator that does not appear in URL-safe base64 encoding and can
 /// appear in URLs without percent-encoding.
 pub const TB64_DELIM: char = '~';
 
+/// The 64 characters of the URL-safe base64 alphabet, in order of their
+/// values, which are the characters allowed in tags and values.
+///
+/// Useful for building regexes and input masks. It does not contain
+/// [TB64_DELIM].
+pub const URL_SAFE_ALPHABET: &str =
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Scheme byte of a value from [TaggedBase64::new_compressed] which is
+/// stored uncompressed.
+pub const COMPRESSION_NONE: u8 = 0;
+
+/// Scheme byte of a value from [TaggedBase64::new_compressed] which is
+/// run-length encoded.
+pub const COMPRESSION_RLE: u8 = 1;
+
 /// Base 64 engine configured for TaggedBase64.
 pub const BASE64: GeneralPurpose = GeneralPurpose::new(&URL_SAFE, NO_PAD);
 
+/// Lookup table for the CRC-8 used in checksums (polynomial 0x07, initial
+/// value 0, no reflection), built at compile time.
+const CRC8_TABLE: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Continues a CRC-8 computation over `bytes`.
+const fn crc8(mut crc: u8, bytes: &[u8]) -> u8 {
+    let mut i = 0;
+    while i < bytes.len() {
+        crc = CRC8_TABLE[(crc ^ bytes[i]) as usize];
+        i += 1;
+    }
+    crc
+}
+
+/// Constructs a [TaggedBase64] from a tag and value known at compile time.
+///
+/// The tag is checked and the checksum computed at compile time, so an
+/// invalid tag is a compile error. Only copying the tag and value into the
+/// owned result happens at run time.
+///
+/// ```
+/// use tagged_base64::{tagged_base64, TaggedBase64};
+///
+/// let genesis = tagged_base64!("COMMIT", b"genesis commitment");
+/// assert_eq!(genesis, TaggedBase64::new("COMMIT", b"genesis commitment").unwrap());
+/// ```
+///
+/// ```compile_fail
+/// # use tagged_base64::tagged_base64;
+/// let invalid = tagged_base64!("NOT~OK", b"");
+/// ```
+#[macro_export]
+macro_rules! tagged_base64 {
+    ($tag:expr, $value:expr) => {{
+        const TB64: $crate::ConstTaggedBase64 = $crate::ConstTaggedBase64::new($tag, $value);
+        $crate::TaggedBase64::from(TB64)
+    }};
+}
+
+/// A tag and value known at compile time, with their checksum, for use by
+/// [macro@tagged_base64].
+///
+/// The checksum is always computed from the tag and value, so this cannot
+/// be used to build a TaggedBase64 with a mismatched checksum.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug)]
+pub struct ConstTaggedBase64 {
+    tag: &'static str,
+    value: &'static [u8],
+    checksum: u8,
+}
+
+impl ConstTaggedBase64 {
+    /// Checks the tag and computes the checksum. In a `const` item, an
+    /// invalid tag is a compile error.
+    pub const fn new(tag: &'static str, value: &'static [u8]) -> Self {
+        assert!(
+            TaggedBase64::is_safe_base64_tag(tag),
+            "tag contains characters which are not URL-safe base64"
+        );
+        Self {
+            tag,
+            value,
+            checksum: TaggedBase64::calc_checksum(tag, value),
+        }
+    }
+}
+
+impl From<ConstTaggedBase64> for TaggedBase64 {
+    fn from(tb64: ConstTaggedBase64) -> Self {
+        TaggedBase64 {
+            tag: tb64.tag.into(),
+            value: tb64.value.to_vec(),
+            checksum: tb64.checksum,
+        }
+    }
+}
+
 /// A structure holding a string tag, vector of bytes, and a checksum
 /// covering the tag and the bytes.
+///
+/// Values are ordered by tag and then by value, so they can be used as
+/// keys of a `BTreeMap`. In human-readable serde formats such as JSON they
+/// serialize as strings, so such a map has the canonical tagged strings as
+/// its keys.
 #[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(
     feature = "ark-serialize",
     derive(CanonicalSerialize, CanonicalDeserialize)
 )]
 pub struct TaggedBase64 {
-    tag: String,
+    tag: SharedTag,
     value: Vec<u8>,
     checksum: u8,
 }
@@ -170,7 +381,7 @@ impl Serialize for TaggedBase64 {
         if serializer.is_human_readable() {
             // If we are serializing to a human-readable format, be nice and just display the
             // tagged base 64 as a string.
-            Serialize::serialize(&self.to_string(), serializer)
+            serializer.collect_str(self)
         } else {
             // For binary formats, convert to bytes (using CanonicalSerialize) and write the bytes.
             let mut bytes = Vec::new();
@@ -206,221 +417,2196 @@ impl<'a> Deserialize<'a> for TaggedBase64 {
     }
 }
 
-/// JavaScript-compatible wrapper for TaggedBase64
+/// Wrapper for TaggedBase64 which serializes as a structure with explicit
+/// fields rather than as a compact string.
 ///
-/// The primary difference is that JsTaggedBase64 returns errors
-/// of type JsValue.
-#[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
+/// This is useful for debugging and for human-edited configuration. In
+/// JSON, for example, it looks like
+/// ```text
+///    { "tag": "KEY", "value": "cHVibGljIGtleSBiaXRz", "checksum": 10 }
+/// ```
+/// where `value` is the base64 encoding of the value alone. The checksum
+/// is verified when deserializing.
+#[cfg(feature = "serde")]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct JsTaggedBase64 {
-    tb64: TaggedBase64,
-}
+pub struct TaggedBase64Verbose(pub TaggedBase64);
 
-#[derive(Debug, Snafu)]
-pub enum Tb64Error {
-    /// An invalid character was found in the tag.
-    InvalidTag,
-    /// Missing delimiter.
-    MissingDelimiter,
-    /// Missing checksum in value.
-    MissingChecksum,
-    #[snafu(display("invalid base 64: {message}"))]
-    Base64 { message: String },
-    /// The checksum was truncated or did not match.
-    InvalidChecksum,
-    /// The data did not encode the expected type.
-    InvalidData,
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct VerboseFields {
+    tag: String,
+    value: String,
+    checksum: u8,
 }
 
-impl From<base64::DecodeError> for Tb64Error {
-    fn from(err: base64::DecodeError) -> Self {
-        Self::Base64 {
-            message: err.to_string(),
+#[cfg(feature = "serde")]
+impl Serialize for TaggedBase64Verbose {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        VerboseFields {
+            tag: self.0.tag.to_string(),
+            value: TaggedBase64::encode_raw(&self.0.value),
+            checksum: self.0.checksum,
         }
+        .serialize(serializer)
     }
 }
 
-/// Converts a TaggedBase64 value to a String.
-#[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
-pub fn to_string(tb64: &TaggedBase64) -> String {
-    let value = &mut tb64.value.clone();
-    value.push(tb64.checksum);
-    format!(
-        "{}{}{}",
-        tb64.tag,
-        TB64_DELIM,
-        TaggedBase64::encode_raw(value)
-    )
+#[cfg(feature = "serde")]
+impl<'a> Deserialize<'a> for TaggedBase64Verbose {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        let fields = VerboseFields::deserialize(deserializer)?;
+        let value = TaggedBase64::decode_raw(&fields.value).map_err(D::Error::custom)?;
+        TaggedBase64::from_parts(fields.tag, value, fields.checksum)
+            .map(Self)
+            .map_err(D::Error::custom)
+    }
 }
 
-impl From<&TaggedBase64> for String {
-    fn from(tb64: &TaggedBase64) -> Self {
-        to_string(tb64)
+#[cfg(feature = "serde")]
+impl From<TaggedBase64> for TaggedBase64Verbose {
+    fn from(tb64: TaggedBase64) -> Self {
+        Self(tb64)
     }
 }
 
-/// Produces the string of a TaggedBase64 value by concatenating the
-/// tag, a delimeter, and the base64 encoding of the value and
-/// checksum.
-impl fmt::Display for TaggedBase64 {
+#[cfg(feature = "serde")]
+impl From<TaggedBase64Verbose> for TaggedBase64 {
+    fn from(verbose: TaggedBase64Verbose) -> Self {
+        verbose.0
+    }
+}
+
+/// Wrapper for TaggedBase64 which is formatted and parsed with a 4-byte
+/// CRC32 checksum, as by [TaggedBase64::to_string_crc32] and
+/// [TaggedBase64::parse_crc32].
+///
+/// The checksum is part of the string form, not the value, so the wrapper
+/// is what keeps a value in the CRC32 form through a round trip: it is
+/// returned by [TaggedBase64::new_crc32] and [TaggedBase64::parse_crc32],
+/// and its [Display](fmt::Display) uses the CRC32 form, whereas the inner
+/// TaggedBase64 displays with the default CRC8 checksum.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TaggedBase64Crc32(pub TaggedBase64);
+
+impl fmt::Display for TaggedBase64Crc32 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", to_string(self))
+        f.write_str(&self.0.to_string_crc32())
     }
 }
 
-impl FromStr for TaggedBase64 {
+impl FromStr for TaggedBase64Crc32 {
     type Err = Tb64Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Self::parse(s)
+        TaggedBase64::parse_crc32(s)
     }
 }
 
-/// Produces the string of a TaggedBase64 value by concatenating the
-/// tag, a delimeter, and the base64 encoding of the value and
-/// checksum.
-impl fmt::Display for JsTaggedBase64 {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.tb64)
+impl From<TaggedBase64> for TaggedBase64Crc32 {
+    fn from(tb64: TaggedBase64) -> Self {
+        Self(tb64)
     }
 }
 
-impl PartialEq<TaggedBase64> for JsTaggedBase64 {
-    fn eq(&self, other: &TaggedBase64) -> bool {
-        self.tb64 == *other
+impl From<TaggedBase64Crc32> for TaggedBase64 {
+    fn from(crc32: TaggedBase64Crc32) -> Self {
+        crc32.0
     }
 }
 
-impl TaggedBase64 {
-    /// Constructs a TaggedBase64 from a tag and array of bytes. The tag
-    /// must be URL-safe (alphanumeric with hyphen and underscore). The
-    /// byte values are unconstrained.
-    ///    ```ignored
-    ///    use TaggedBase64;
-    ///    let tb64 = TaggedBase64::new("TAG-YOURE-IT", b"datadatadata");
-    ///    ```
-    pub fn new(tag: &str, value: &[u8]) -> Result<TaggedBase64, Tb64Error> {
-        if TaggedBase64::is_safe_base64_tag(tag) {
-            let cs = TaggedBase64::calc_checksum(tag, value);
-            Ok(TaggedBase64 {
-                tag: tag.to_string(),
-                value: value.to_vec(),
-                checksum: cs,
-            })
+/// Wrapper for TaggedBase64 which deserializes leniently from either a
+/// tagged string or bare base64, for ingesting data from systems which
+/// emit bare base64.
+///
+/// In human-readable formats, a string containing the delimiter is parsed
+/// as a TaggedBase64, checksum and all. Any other string is decoded as
+/// bare base64 and given an empty tag and a freshly computed checksum, as
+/// by [TaggedBase64::from_base64].
+///
+/// Bare base64 never contains the delimiter, so the two forms cannot be
+/// confused with each other, but the bare form is ambiguous in other ways:
+/// the tag is lost, there is no checksum to catch corruption, and if the
+/// source appended a checksum of its own it becomes part of the value. A
+/// tagged string with the delimiter missing is also read as a bare value.
+///
+/// It always serializes as a tagged string, and binary formats are handled
+/// exactly as for TaggedBase64.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LenientTaggedBase64(pub TaggedBase64);
+
+#[cfg(feature = "serde")]
+impl Serialize for LenientTaggedBase64 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> Deserialize<'a> for LenientTaggedBase64 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        if !deserializer.is_human_readable() {
+            return TaggedBase64::deserialize(deserializer).map(Self);
+        }
+        // Deserialize an owned `String`, for the reason given in the impl for TaggedBase64.
+        let s: String = Deserialize::deserialize(deserializer)?;
+        if s.contains(TB64_DELIM) {
+            TaggedBase64::parse(&s)
         } else {
-            Err(Tb64Error::InvalidTag)
+            TaggedBase64::from_base64("", &s)
         }
+        .map(Self)
+        .map_err(D::Error::custom)
     }
+}
 
-    /// Parses a string of the form tag~value into a TaggedBase64 value.
-    ///
-    /// The tag is restricted to URL-safe base64 ASCII characters. The tag
-    /// may be empty. The delimiter is required.
-    ///
-    /// The value is a base64-encoded string, using the URL-safe character
-    /// set, and no padding is used.
-    pub fn parse(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
-        // Would be convenient to use split_first() here. Alas, not stable yet.
-        let delim_pos = tb64.find(TB64_DELIM).ok_or(Tb64Error::MissingDelimiter)?;
-        let (tag, delim_b64) = tb64.split_at(delim_pos);
+#[cfg(feature = "serde")]
+impl From<TaggedBase64> for LenientTaggedBase64 {
+    fn from(tb64: TaggedBase64) -> Self {
+        Self(tb64)
+    }
+}
 
-        if !TaggedBase64::is_safe_base64_tag(tag) {
-            return Err(Tb64Error::InvalidTag);
-        }
+#[cfg(feature = "serde")]
+impl From<LenientTaggedBase64> for TaggedBase64 {
+    fn from(lenient: LenientTaggedBase64) -> Self {
+        lenient.0
+    }
+}
 
-        // Remove the delimiter.
-        let mut iter = delim_b64.chars();
-        iter.next();
-        let value = iter.as_str();
-        if value.is_empty() {
-            return Err(Tb64Error::MissingChecksum);
-        }
+/// JavaScript-compatible wrapper for TaggedBase64
+///
+/// The primary difference is that JsTaggedBase64 returns errors
+/// of type JsValue.
+#[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsTaggedBase64 {
+    tb64: TaggedBase64,
+}
 
-        // Note: 'printf' debugging is possible like this:
-        //    use web_sys;
-        //    web_sys::console::log_1(&format!("+ {}", &tb64).into());
+/// An immutable TaggedBase64 which may keep its string form, for values
+/// which are displayed many times.
+///
+/// [CachedTaggedBase64::display] borrows the cached string, or formats a
+/// new one if the string was not cached.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CachedTaggedBase64 {
+    tb64: TaggedBase64,
+    cached: Option<String>,
+}
 
-        // Base64 decode the value.
-        let bytes = TaggedBase64::decode_raw(value)?;
-        let penultimate = bytes.len() - 1;
-        let cs = bytes[penultimate];
-        if cs == TaggedBase64::calc_checksum(tag, &bytes[..penultimate]) {
-            Ok(TaggedBase64 {
-                tag: tag.to_string(),
-                value: bytes[..penultimate].to_vec(),
-                checksum: cs,
-            })
-        } else {
-            Err(Tb64Error::InvalidChecksum)
-        }
+impl CachedTaggedBase64 {
+    /// Wraps a TaggedBase64, formatting and caching its string form now.
+    pub fn new(tb64: TaggedBase64) -> Self {
+        let cached = Some(tb64.to_string());
+        Self { tb64, cached }
     }
 
-    fn calc_checksum(tag: &str, value: &[u8]) -> u8 {
-        let mut crc8 = CRC::crc8();
-        crc8.digest(&tag);
-        crc8.digest(&value);
-        (crc8.get_crc() as u8) ^ (value.len() as u8)
+    /// Wraps a TaggedBase64 without caching its string form.
+    pub fn uncached(tb64: TaggedBase64) -> Self {
+        Self { tb64, cached: None }
     }
 
-    /// Returns true for characters permitted in URL-safe base64 encoding,
-    /// and false otherwise.
-    pub fn is_safe_base64_ascii(c: char) -> bool {
-        c.is_ascii_alphanumeric() || (c == '-') || (c == '_')
+    /// Returns true if the string form is cached.
+    pub fn is_cached(&self) -> bool {
+        self.cached.is_some()
     }
 
-    /// Checks that an ASCII byte is safe for use in the tag of a
-    /// TaggedBase64. Because the tags are merely intended to be mnemonic,
-    /// there's no need to support a large and visually ambiguous
-    /// character set.
-    pub fn is_safe_base64_tag(tag: &str) -> bool {
-        tag.chars().all(TaggedBase64::is_safe_base64_ascii)
+    /// Gets the string form, borrowing it if it is cached and formatting it
+    /// otherwise.
+    pub fn display(&self) -> Cow<'_, str> {
+        match &self.cached {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(self.tb64.to_string()),
+        }
     }
 
-    /// Gets the tag of a TaggedBase64 instance.
-    pub fn tag(&self) -> String {
-        self.tag.clone()
+    /// Unwraps the TaggedBase64.
+    pub fn into_inner(self) -> TaggedBase64 {
+        self.tb64
     }
+}
 
-    /// Sets the tag of a TaggedBase64 instance.
-    pub fn set_tag(&mut self, tag: &str) {
-        assert!(TaggedBase64::is_safe_base64_tag(tag));
-        self.tag = tag.to_string();
-        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+impl Deref for CachedTaggedBase64 {
+    type Target = TaggedBase64;
+
+    fn deref(&self) -> &TaggedBase64 {
+        &self.tb64
     }
+}
 
-    /// Gets the value of a TaggedBase64 instance.
-    pub fn value(&self) -> Vec<u8> {
-        self.value.clone()
+impl fmt::Display for CachedTaggedBase64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.cached {
+            Some(s) => f.write_str(s),
+            None => fmt::Display::fmt(&self.tb64, f),
+        }
     }
+}
 
-    /// Sets the value of a TaggedBase64 instance.
-    pub fn set_value(&mut self, value: &[u8]) {
-        self.value = value.to_vec();
-        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+/// Serializes the same way as the wrapped TaggedBase64, but serializes the
+/// cached string form as a borrowed `str` in human-readable formats.
+#[cfg(feature = "serde")]
+impl Serialize for CachedTaggedBase64 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.cached {
+            Some(s) if serializer.is_human_readable() => serializer.serialize_str(s),
+            _ => self.tb64.serialize(serializer),
+        }
     }
+}
 
-    /// Wraps the underlying base64 encoder.
-    // WASM doesn't support the most general type.
-    //
-    // pub fn encode_raw<T: ?Sized + AsRef<[u8]>>(input: &T) -> String;
-    pub fn encode_raw(input: &[u8]) -> String {
-        BASE64.encode(input)
+/// A tag which has been checked to be safe for use in a TaggedBase64.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Tag(String);
+
+impl Tag {
+    /// Constructs a Tag, checking that it contains only URL-safe base64
+    /// characters.
+    pub fn new(tag: &str) -> Result<Tag, Tb64Error> {
+        TaggedBase64::check_tag(tag)?;
+        Ok(Tag(tag.to_string()))
     }
-    /// Wraps the underlying base64 decoder.
-    pub fn decode_raw(value: &str) -> Result<Vec<u8>, Tb64Error> {
-        Ok(BASE64.decode(value)?)
+
+    /// Gets the tag as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
-impl AsRef<[u8]> for TaggedBase64 {
-    fn as_ref(&self) -> &[u8] {
-        &self.value
+impl FromStr for Tag {
+    type Err = Tb64Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
     }
 }
 
-/// Converts any object that supports the Display trait to a JsValue for
-/// passing to Javascript.
-///
+impl AsRef<str> for Tag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A policy deciding which characters are allowed in a tag.
+///
+/// The default policy allows the URL-safe base64 characters. Whatever the
+/// policy, the delimiter is never allowed in a tag.
+#[derive(Clone, Copy, Debug)]
+pub struct TagPolicy {
+    is_allowed: fn(char) -> bool,
+}
+
+impl TagPolicy {
+    /// Constructs a policy which allows the characters for which
+    /// `is_allowed` returns true.
+    pub const fn new(is_allowed: fn(char) -> bool) -> Self {
+        Self { is_allowed }
+    }
+
+    /// A policy which allows only ASCII letters and digits.
+    pub const fn strict_alnum() -> Self {
+        Self::new(|c| c.is_ascii_alphanumeric())
+    }
+
+    /// Checks that a tag is allowed by this policy, reporting the first
+    /// character which is not.
+    ///
+    /// The delimiter is never allowed, whatever the policy says. If it is
+    /// the first bad character, the error is
+    /// [TagContainsDelimiter](Tb64Error::TagContainsDelimiter).
+    pub fn check(&self, tag: &str) -> Result<(), Tb64Error> {
+        match tag
+            .char_indices()
+            .find(|(_, c)| *c == TB64_DELIM || !(self.is_allowed)(*c))
+        {
+            None => Ok(()),
+            Some((_, TB64_DELIM)) => Err(Tb64Error::TagContainsDelimiter),
+            Some((position, character)) if !character.is_ascii() => Err(Tb64Error::NonAsciiTag {
+                position,
+                character,
+            }),
+            Some((position, character)) => Err(Tb64Error::InvalidTag {
+                position,
+                character,
+            }),
+        }
+    }
+}
+
+impl Default for TagPolicy {
+    fn default() -> Self {
+        Self::new(TaggedBase64::is_safe_base64_ascii)
+    }
+}
+
+/// A table of short abbreviations which stand in for long tags, for
+/// contexts where the length of the string matters, such as URLs.
+///
+/// See [TaggedBase64::to_string_abbreviated] and
+/// [TaggedBase64::parse_abbreviated].
+#[derive(Clone, Debug, Default)]
+pub struct TagAbbreviations {
+    abbreviations: BTreeMap<String, String>,
+    expansions: BTreeMap<String, String>,
+}
+
+impl TagAbbreviations {
+    /// Constructs an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an abbreviation for a tag.
+    ///
+    /// Both must be safe tags, and neither may already appear in the
+    /// table, as either a tag or an abbreviation, so that every string
+    /// expands unambiguously.
+    pub fn insert(&mut self, tag: &str, abbreviation: &str) -> Result<(), Tb64Error> {
+        TaggedBase64::check_tag(tag)?;
+        TaggedBase64::check_tag(abbreviation)?;
+        for t in [tag, abbreviation] {
+            if self.abbreviations.contains_key(t) || self.expansions.contains_key(t) {
+                return Err(Tb64Error::AbbreviationConflict);
+            }
+        }
+        self.abbreviations
+            .insert(tag.to_string(), abbreviation.to_string());
+        self.expansions
+            .insert(abbreviation.to_string(), tag.to_string());
+        Ok(())
+    }
+
+    /// Gets the abbreviation for a tag, or the tag itself if it has none.
+    pub fn abbreviate<'a>(&'a self, tag: &'a str) -> &'a str {
+        self.abbreviations.get(tag).map_or(tag, String::as_str)
+    }
+
+    /// Gets the tag for an abbreviation, or the argument itself if it is
+    /// not an abbreviation.
+    pub fn expand<'a>(&'a self, abbreviation: &'a str) -> &'a str {
+        self.expansions
+            .get(abbreviation)
+            .map_or(abbreviation, String::as_str)
+    }
+}
+
+/// A set of tags shared between many TaggedBase64 values.
+///
+/// Values constructed with [TaggedBase64::new_interned] reference the
+/// interner's copy of the tag instead of allocating their own, which saves
+/// memory when a few tags are used by very many values.
+///
+/// Sharing relies on atomic reference counting. On targets without atomic
+/// pointer operations, each value gets its own copy of the tag, and the
+/// interner only saves checking tags it has already seen.
+#[derive(Clone, Debug, Default)]
+pub struct TagInterner {
+    tags: BTreeSet<SharedTag>,
+}
+
+impl TagInterner {
+    /// Constructs an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of distinct tags interned.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Returns true if no tags have been interned.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    fn intern(&mut self, tag: &str) -> Result<SharedTag, Tb64Error> {
+        if let Some(interned) = self.tags.get(tag) {
+            return Ok(interned.clone());
+        }
+        TaggedBase64::check_tag(tag)?;
+        let interned = SharedTag::from(tag);
+        self.tags.insert(interned.clone());
+        Ok(interned)
+    }
+}
+
+/// The tag of a TaggedBase64, which may be shared through a [TagInterner].
+#[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
+struct SharedTag(TagStorage);
+
+/// Reference counted, so that clones and interned values share the tag.
+#[cfg(target_has_atomic = "ptr")]
+type TagStorage = Arc<str>;
+
+/// Targets without atomics have no `Arc`, so each value owns its tag.
+#[cfg(not(target_has_atomic = "ptr"))]
+type TagStorage = String;
+
+impl core::borrow::Borrow<str> for SharedTag {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl Deref for SharedTag {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for SharedTag {
+    fn from(tag: &str) -> Self {
+        Self(tag.into())
+    }
+}
+
+impl From<String> for SharedTag {
+    fn from(tag: String) -> Self {
+        Self(tag.into())
+    }
+}
+
+impl fmt::Debug for SharedTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl fmt::Display for SharedTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+// Serialized exactly like the String which used to hold the tag.
+#[cfg(feature = "ark-serialize")]
+impl CanonicalSerialize for SharedTag {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.as_bytes().serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.as_bytes().serialized_size(compress)
+    }
+}
+
+#[cfg(feature = "ark-serialize")]
+impl Valid for SharedTag {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ark-serialize")]
+impl CanonicalDeserialize for SharedTag {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let tag = String::deserialize_with_mode(reader, compress, validate)?;
+        Ok(Self(tag.into()))
+    }
+}
+
+/// Errors which can occur when constructing or parsing a TaggedBase64.
+///
+/// New variants may be added without a major version bump, so matches
+/// outside this crate need a wildcard arm.
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum Tb64Error {
+    /// An invalid character was found in the tag at the given position.
+    #[snafu(display("invalid character {character:?} at position {position} in the tag"))]
+    InvalidTag { position: usize, character: char },
+    /// The tag is valid, but it is not the one expected.
+    UnexpectedTag,
+    /// The tag contains the delimiter '~'. Was a whole tag~value string passed as the tag?
+    TagContainsDelimiter,
+    /// The tag contains a non-ASCII character. Tags must be ASCII.
+    #[snafu(display(
+        "non-ASCII character {character:?} at position {position} in the tag; tags must be ASCII"
+    ))]
+    NonAsciiTag { position: usize, character: char },
+    /// The tag or abbreviation is already in the abbreviation table.
+    AbbreviationConflict,
+    /// The separator is a base64 character or the delimiter.
+    InvalidSeparator,
+    /// The delimiter is a base64 character, or is not a single character.
+    InvalidDelimiter,
+    /// Missing delimiter.
+    MissingDelimiter,
+    /// Missing checksum in value.
+    MissingChecksum,
+    #[snafu(display("invalid base 64: {message}"))]
+    Base64 { message: String },
+    /// A byte outside the base64 alphabet was found in the value.
+    #[snafu(display("invalid base 64: Invalid symbol {byte}, offset {offset}."))]
+    InvalidByte { offset: usize, byte: u8 },
+    /// The last symbol of the value has nonzero bits which do not fit in
+    /// a whole byte, which suggests corruption or truncation.
+    #[snafu(display("invalid base 64: Invalid last symbol {byte}, offset {offset}."))]
+    InvalidLastSymbol { offset: usize, byte: u8 },
+    /// The checksum was truncated or did not match.
+    InvalidChecksum,
+    /// The data did not encode the expected type.
+    InvalidData,
+    /// The value did not have the expected length.
+    #[snafu(display("expected a value of {expected} bytes, found {actual} bytes"))]
+    WrongLength { expected: usize, actual: usize },
+    /// The output buffer is too small.
+    #[snafu(display("the output needs {needed} bytes but the buffer has {available}"))]
+    BufferTooSmall { needed: usize, available: usize },
+    /// There was more input after a complete value.
+    #[snafu(display("unexpected data after the value at position {position}"))]
+    TrailingData { position: usize },
+}
+
+/// Non-fatal problems with a valid TaggedBase64, reported by
+/// [TaggedBase64::parse_checked].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Tb64Warning {
+    /// The tag is empty, so it gives no hint of what the value is.
+    EmptyTag,
+    /// The tag is all digits, so it could be mistaken for a number.
+    NumericTag,
+    /// The value is empty; only the checksum is encoded.
+    EmptyValue,
+}
+
+impl fmt::Display for Tb64Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Tb64Warning::EmptyTag => "the tag is empty",
+            Tb64Warning::NumericTag => "the tag is all digits and could be mistaken for a number",
+            Tb64Warning::EmptyValue => "the value is empty",
+        })
+    }
+}
+
+/// The tag and size of a TaggedBase64 string, found by
+/// [TaggedBase64::inspect] without decoding the value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Inspection<'a> {
+    /// The tag, borrowed from the string.
+    pub tag: &'a str,
+    /// The length in bytes of the decoded value, not counting the checksum.
+    pub value_len: usize,
+}
+
+/// Every layer of a TaggedBase64 string, found by [TaggedBase64::dissect]
+/// for debugging tools.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dissection<'a> {
+    /// The tag, borrowed from the string.
+    pub tag: &'a str,
+    /// The base64 encoding of the value and checksum, borrowed from the
+    /// string.
+    pub value_b64: &'a str,
+    /// The decoded value, not including the checksum.
+    pub value: Vec<u8>,
+    /// The checksum found in the string.
+    pub checksum: u8,
+    /// Whether the checksum matches the tag and value.
+    pub checksum_valid: bool,
+}
+
+impl From<base64::DecodeError> for Tb64Error {
+    fn from(err: base64::DecodeError) -> Self {
+        match err {
+            base64::DecodeError::InvalidByte(offset, byte) => Self::InvalidByte { offset, byte },
+            base64::DecodeError::InvalidLastSymbol(offset, byte) => {
+                Self::InvalidLastSymbol { offset, byte }
+            }
+            _ => Self::Base64 {
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+/// Converts a TaggedBase64 value to a String.
+///
+/// The string is allocated once, at its final length, and the tag, delimiter
+/// and encoding are written directly into it.
+#[cfg_attr(all(target_arch = "wasm32", feature = "wasm-bindgen"), wasm_bindgen)]
+pub fn to_string(tb64: &TaggedBase64) -> String {
+    let mut s = String::with_capacity(tb64.encoded_len());
+    write!(s, "{}", tb64).expect("writing to a String does not fail");
+    s
+}
+
+/// Encodes each (tag, value) pair as a tagged base64 string, for writing
+/// a batch of values, for example one per line to be read back with
+/// [TaggedBase64::parse_many].
+///
+/// An invalid tag gives an error for that pair only.
+pub fn encode_all<'a, I>(items: I) -> impl Iterator<Item = Result<String, Tb64Error>> + 'a
+where
+    I: IntoIterator<Item = (&'a str, &'a [u8])> + 'a,
+{
+    items
+        .into_iter()
+        .map(|(tag, value)| TaggedBase64::new(tag, value).map(|tb64| to_string(&tb64)))
+}
+
+impl From<&TaggedBase64> for String {
+    fn from(tb64: &TaggedBase64) -> Self {
+        to_string(tb64)
+    }
+}
+
+/// Produces the string of a TaggedBase64 value by concatenating the
+/// tag, a delimeter, and the base64 encoding of the value and
+/// checksum.
+///
+/// The encoding is written to the formatter incrementally, without
+/// allocating.
+impl fmt::Display for TaggedBase64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.tag)?;
+        f.write_char(TB64_DELIM)?;
+        self.write_encoded_value(f)
+    }
+}
+
+impl FromStr for TaggedBase64 {
+    type Err = Tb64Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Produces the string of a TaggedBase64 value by concatenating the
+/// tag, a delimeter, and the base64 encoding of the value and
+/// checksum.
+impl fmt::Display for JsTaggedBase64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.tb64)
+    }
+}
+
+/// Compares a string with the canonical string form of a TaggedBase64, as
+/// produced by [Display](fmt::Display).
+///
+/// Only `str == TaggedBase64` is provided, not the reverse, so that
+/// `TaggedBase64 == _` still has a single candidate and the right-hand side
+/// of `assert_eq!(tb64, s.parse().unwrap())` can be inferred.
+impl PartialEq<TaggedBase64> for str {
+    fn eq(&self, other: &TaggedBase64) -> bool {
+        other.eq_str(self)
+    }
+}
+
+/// Compares a string with the canonical string form of a TaggedBase64, as
+/// produced by [Display](fmt::Display).
+impl PartialEq<TaggedBase64> for &str {
+    fn eq(&self, other: &TaggedBase64) -> bool {
+        other.eq_str(self)
+    }
+}
+
+impl From<TaggedBase64> for JsTaggedBase64 {
+    fn from(tb64: TaggedBase64) -> Self {
+        Self { tb64 }
+    }
+}
+
+impl PartialEq<TaggedBase64> for JsTaggedBase64 {
+    fn eq(&self, other: &TaggedBase64) -> bool {
+        self.tb64 == *other
+    }
+}
+
+impl TaggedBase64 {
+    /// Constructs a TaggedBase64 from a tag and array of bytes. The tag
+    /// must be URL-safe (alphanumeric with hyphen and underscore). The
+    /// byte values are unconstrained.
+    ///    ```ignored
+    ///    use TaggedBase64;
+    ///    let tb64 = TaggedBase64::new("TAG-YOURE-IT", b"datadatadata");
+    ///    ```
+    pub fn new(tag: &str, value: &[u8]) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::new_with_policy(tag, value, &TagPolicy::default())
+    }
+
+    /// Constructs a TaggedBase64 like [TaggedBase64::new], but with the
+    /// tag stored in `interner`, so that all values constructed with the
+    /// same interner and tag share one copy of the tag.
+    ///
+    /// On targets without atomic pointer operations the tag is copied; see
+    /// [TagInterner].
+    pub fn new_interned(
+        interner: &mut TagInterner,
+        tag: &str,
+        value: &[u8],
+    ) -> Result<TaggedBase64, Tb64Error> {
+        let tag = interner.intern(tag)?;
+        Ok(TaggedBase64 {
+            checksum: TaggedBase64::calc_checksum(&tag, value),
+            tag,
+            value: value.to_vec(),
+        })
+    }
+
+    /// Constructs a TaggedBase64 like [TaggedBase64::new], with the value
+    /// formed by concatenating `chunks`, for pipelines which produce the
+    /// value in pieces.
+    ///
+    /// The chunks are copied straight into the value, and the checksum is
+    /// computed once at the end.
+    pub fn from_chunks<I>(tag: &str, chunks: I) -> Result<TaggedBase64, Tb64Error>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        TaggedBase64::check_tag(tag)?;
+        let mut value = Vec::new();
+        for chunk in chunks {
+            value.extend_from_slice(chunk.as_ref());
+        }
+        Ok(TaggedBase64 {
+            checksum: TaggedBase64::calc_checksum(tag, &value),
+            tag: tag.into(),
+            value,
+        })
+    }
+
+    /// Constructs a TaggedBase64 with the given checksum instead of the
+    /// computed one, for tests of code which must reject corrupted values.
+    ///
+    /// The tag is still checked. This is only available with the
+    /// `test-util` feature.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn new_with_raw_checksum(
+        tag: &str,
+        value: &[u8],
+        checksum: u8,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::check_tag(tag)?;
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.to_vec(),
+            checksum,
+        })
+    }
+
+    /// Parses a string of the form tag~value into a TaggedBase64 value.
+    ///
+    /// The tag is restricted to URL-safe base64 ASCII characters. The tag
+    /// may be empty. The delimiter is required.
+    ///
+    /// The value is a base64-encoded string, using the URL-safe character
+    /// set, and no padding is used.
+    pub fn parse(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+
+        // Note: 'printf' debugging is possible like this:
+        //    use web_sys;
+        //    web_sys::console::log_1(&format!("+ {}", &tb64).into());
+
+        TaggedBase64::decode_checked(tag, value)
+    }
+
+    /// Parses a string of the form tag~value which may be wrapped in one
+    /// pair of double quotes, as when it is copied from JSON.
+    ///
+    /// Straight quotes (`"`) and smart quotes (`“` and `”`) are accepted.
+    /// [TaggedBase64::parse] remains strict.
+    pub fn parse_unquoted(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let unquoted = [('"', '"'), ('“', '”')]
+            .into_iter()
+            .find_map(|(open, close)| tb64.strip_prefix(open)?.strip_suffix(close))
+            .unwrap_or(tb64);
+        TaggedBase64::parse(unquoted)
+    }
+
+    /// Parses a string of the form tag~value with any safe tag, including
+    /// an empty one, while still verifying the value and checksum.
+    ///
+    /// This is the same as [TaggedBase64::parse]. The name makes explicit
+    /// that the tag is not compared to an expected value, which suits
+    /// generic inspectors and migrations between tag conventions. Use
+    /// [TaggedBase64::parse_expecting] when the tag is known.
+    pub fn parse_any_tag(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::parse(tb64)
+    }
+
+    /// Parses a string of the form tag~value, requiring the tag to be
+    /// `expected`.
+    ///
+    /// Returns [Tb64Error::UnexpectedTag] if the tag is different. The tag is
+    /// checked before the value is decoded.
+    pub fn parse_expecting(tb64: &str, expected: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        if tag != expected {
+            return Err(Tb64Error::UnexpectedTag);
+        }
+        TaggedBase64::decode_checked(tag, value)
+    }
+
+    /// Parses a string of the form tag~value, requiring the tag to be one
+    /// of `allowed`, for dispatchers with a closed set of types.
+    ///
+    /// Returns [Tb64Error::UnexpectedTag] if the tag is not in the set. The
+    /// tag is checked before the value is decoded.
+    pub fn parse_in_set(tb64: &str, allowed: &[&str]) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        if !allowed.contains(&tag) {
+            return Err(Tb64Error::UnexpectedTag);
+        }
+        TaggedBase64::decode_checked(tag, value)
+    }
+
+    /// Parses a string which must be exactly one value of the form
+    /// tag~value, with nothing after it.
+    ///
+    /// [TaggedBase64::parse] also fails on trailing data, but with whatever
+    /// error decoding it happens to produce. This instead reports
+    /// [Tb64Error::TrailingData] with the byte position of the first
+    /// character after the value which is not base64, such as trailing
+    /// whitespace or a second `~value`.
+    pub fn parse_exact(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        if let Some(end) = value.find(|c| !TaggedBase64::is_safe_base64_ascii(c)) {
+            return Err(Tb64Error::TrailingData {
+                position: tb64.len() - value.len() + end,
+            });
+        }
+        TaggedBase64::decode_checked(tag, value)
+    }
+
+    /// Parses a string of the form tag~value like [TaggedBase64::parse],
+    /// but decodes the value into `buf`, reusing its allocation, for
+    /// processing a stream of values with a single buffer.
+    ///
+    /// A TaggedBase64 owns its value, so one cannot be returned without
+    /// allocating. Instead, this returns the tag, borrowed from the input,
+    /// and leaves the value (without the checksum) in `buf`, which is
+    /// cleared first. If parsing fails, the contents of `buf` are
+    /// unspecified.
+    pub fn parse_reuse<'a>(tb64: &'a str, buf: &mut Vec<u8>) -> Result<&'a str, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        buf.clear();
+        BASE64.decode_vec(value, buf)?;
+        let cs = buf.pop().ok_or(Tb64Error::MissingChecksum)?;
+        if cs == TaggedBase64::calc_checksum(tag, buf) {
+            Ok(tag)
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Parses each line of `input`, such as the contents of a file with one
+    /// TaggedBase64 per line.
+    ///
+    /// There is one result per line, so the position of a result is the
+    /// index of its line. Lines may end with `\n` or `\r\n`. A blank line
+    /// is an error, since it is not a valid TaggedBase64.
+    pub fn parse_many(input: &str) -> impl Iterator<Item = Result<TaggedBase64, Tb64Error>> + '_ {
+        input.lines().map(TaggedBase64::parse)
+    }
+
+    /// Parses each line of `input` like [TaggedBase64::parse_many], and
+    /// returns the index and error of every line which fails.
+    ///
+    /// An empty result means that every line is valid.
+    pub fn validate_all(input: &str) -> Vec<(usize, Tb64Error)> {
+        TaggedBase64::parse_many(input)
+            .enumerate()
+            .filter_map(|(line, res)| res.err().map(|err| (line, err)))
+            .collect()
+    }
+
+    /// Parses each line of `input` like [TaggedBase64::parse_many], and
+    /// returns the distinct tags, in the order in which they first appear.
+    ///
+    /// Fails with the error of the first line which does not parse.
+    pub fn distinct_tags(input: &str) -> Result<Vec<String>, Tb64Error> {
+        let mut seen = BTreeSet::new();
+        let mut tags = Vec::new();
+        for tb64 in TaggedBase64::parse_many(input) {
+            let tag = tb64?.tag();
+            if seen.insert(tag.clone()) {
+                tags.push(tag);
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Parses a string of the form tag~value like [TaggedBase64::parse],
+    /// and on a checksum mismatch also returns the expected and found
+    /// checksums, so that a UI can show both.
+    ///
+    /// The second element is `Some((expected, found))` only when the string
+    /// is well formed but its checksum does not match. It is `None` when
+    /// parsing succeeds or fails for another reason.
+    pub fn parse_reporting(tb64: &str) -> (Result<TaggedBase64, Tb64Error>, Option<(u8, u8)>) {
+        let (tag, value) = match TaggedBase64::split(tb64) {
+            Ok(parts) => parts,
+            Err(err) => return (Err(err), None),
+        };
+        let bytes = match TaggedBase64::decode_raw(value) {
+            Ok(bytes) => bytes,
+            Err(err) => return (Err(err), None),
+        };
+        let Some((&found, value)) = bytes.split_last() else {
+            return (Err(Tb64Error::MissingChecksum), None);
+        };
+        let expected = TaggedBase64::calc_checksum(tag, value);
+        if found == expected {
+            (TaggedBase64::new(tag, value), None)
+        } else {
+            (Err(Tb64Error::InvalidChecksum), Some((expected, found)))
+        }
+    }
+
+    /// Parses a string of the form tag~value, like [TaggedBase64::parse],
+    /// and also reports anything suspicious about the result which is
+    /// nevertheless valid.
+    pub fn parse_checked(tb64: &str) -> Result<(TaggedBase64, Vec<Tb64Warning>), Tb64Error> {
+        let parsed = TaggedBase64::parse(tb64)?;
+        let mut warnings = Vec::new();
+        if parsed.tag.is_empty() {
+            warnings.push(Tb64Warning::EmptyTag);
+        } else if parsed.tag.bytes().all(|c| c.is_ascii_digit()) {
+            warnings.push(Tb64Warning::NumericTag);
+        }
+        if parsed.value.is_empty() {
+            warnings.push(Tb64Warning::EmptyValue);
+        }
+        Ok((parsed, warnings))
+    }
+
+    /// Formats the TaggedBase64 with its tag replaced by the abbreviation
+    /// from `abbreviations`, if there is one.
+    ///
+    /// The checksum still covers the full tag, so the result can only be
+    /// read with [TaggedBase64::parse_abbreviated] and the same table.
+    pub fn to_string_abbreviated(&self, abbreviations: &TagAbbreviations) -> String {
+        format!(
+            "{}{}{}",
+            abbreviations.abbreviate(&self.tag),
+            TB64_DELIM,
+            self.encode_value()
+        )
+    }
+
+    /// Parses a string produced by [TaggedBase64::to_string_abbreviated],
+    /// expanding an abbreviated tag back to the full tag.
+    ///
+    /// Tags which are not abbreviations in the table are used as is.
+    pub fn parse_abbreviated(
+        tb64: &str,
+        abbreviations: &TagAbbreviations,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        TaggedBase64::decode_checked(abbreviations.expand(tag), value)
+    }
+
+    /// Parses a string of the form tag~value and reports whether the
+    /// checksum matches.
+    ///
+    /// Unlike [TaggedBase64::parse], this separates corrupted data, which
+    /// gives `Ok(false)`, from input which is not well-formed at all, which
+    /// gives an error for the structural or base64 problem.
+    pub fn checksum_matches(tb64: &str) -> Result<bool, Tb64Error> {
+        match TaggedBase64::verify_str(tb64) {
+            Ok(()) => Ok(true),
+            Err(Tb64Error::InvalidChecksum) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Checks that a string of the form tag~value is valid, including its
+    /// checksum, without allocating.
+    ///
+    /// This accepts and rejects exactly the strings [TaggedBase64::parse]
+    /// does, with the same errors, but decodes the value a piece at a time
+    /// into a buffer on the stack instead of constructing a TaggedBase64.
+    /// [TaggedBase64::looks_valid] is cheaper still, but only checks the
+    /// structure.
+    pub fn verify_str(tb64: &str) -> Result<(), Tb64Error> {
+        // Every chunk except the last is a whole number of 4-symbol groups,
+        // so decoding the chunks separately is equivalent to decoding the
+        // whole value.
+        const CHUNK: usize = 4 * 64;
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        let mut buf = [0u8; CHUNK / 4 * 3];
+        let mut crc = crc8(0, tag.as_bytes());
+        let mut len = 0usize;
+        // The last byte decoded so far, which is the checksum if it turns
+        // out to be the last byte of all.
+        let mut last = None;
+        for (i, chunk) in value.as_bytes().chunks(CHUNK).enumerate() {
+            let n = BASE64.decode_slice(chunk, &mut buf).map_err(|err| {
+                // Report offsets relative to the whole value, as `parse` does.
+                let offset = i * CHUNK;
+                match err {
+                    base64::DecodeSliceError::DecodeError(base64::DecodeError::InvalidByte(
+                        o,
+                        b,
+                    )) => Tb64Error::InvalidByte {
+                        offset: offset + o,
+                        byte: b,
+                    },
+                    base64::DecodeSliceError::DecodeError(
+                        base64::DecodeError::InvalidLastSymbol(o, b),
+                    ) => Tb64Error::InvalidLastSymbol {
+                        offset: offset + o,
+                        byte: b,
+                    },
+                    base64::DecodeSliceError::DecodeError(base64::DecodeError::InvalidLength(
+                        _,
+                    )) => base64::DecodeError::InvalidLength(value.len()).into(),
+                    base64::DecodeSliceError::DecodeError(err) => err.into(),
+                    base64::DecodeSliceError::OutputSliceTooSmall => {
+                        unreachable!("the buffer holds a whole chunk")
+                    }
+                }
+            })?;
+            if let Some((&end, bytes)) = buf[..n].split_last() {
+                if let Some(prev) = last {
+                    crc = crc8(crc, &[prev]);
+                    len += 1;
+                }
+                crc = crc8(crc, bytes);
+                len += bytes.len();
+                last = Some(end);
+            }
+        }
+        let checksum = last.ok_or(Tb64Error::MissingChecksum)?;
+        if checksum == crc ^ (len as u8) {
+            Ok(())
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Gets the tag of a string of the form tag~value without decoding the
+    /// value.
+    ///
+    /// The tag is checked, but the value is not, so a string whose value is
+    /// corrupt still gives its tag.
+    pub fn split_tag(tb64: &str) -> Result<&str, Tb64Error> {
+        TaggedBase64::split(tb64).map(|(tag, _)| tag)
+    }
+
+    /// Gets the tag and the decoded length of the value of a string of the
+    /// form tag~value, without allocating or decoding.
+    ///
+    /// This lets routers dispatch on tag and size cheaply. Like
+    /// [TaggedBase64::split_tag], it does not validate the value; use
+    /// [TaggedBase64::parse] for that.
+    pub fn inspect(tb64: &str) -> Result<Inspection<'_>, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        // Every 4 symbols encode 3 bytes, and a partial group of 2 or 3
+        // symbols encodes 1 or 2 bytes. The last byte is the checksum.
+        let bytes = value.len() / 4 * 3 + (value.len() % 4).saturating_sub(1);
+        let value_len = bytes.checked_sub(1).ok_or(Tb64Error::MissingChecksum)?;
+        Ok(Inspection { tag, value_len })
+    }
+
+    /// Breaks a string of the form tag~value into all of its layers at
+    /// once, for a debugger or REPL which shows each of them.
+    ///
+    /// A checksum mismatch is not an error: it is reported in
+    /// [Dissection::checksum_valid], so that the rest can still be shown.
+    /// Other problems, such as a missing delimiter or invalid base64, are
+    /// errors as in [TaggedBase64::parse].
+    pub fn dissect(tb64: &str) -> Result<Dissection<'_>, Tb64Error> {
+        let (tag, value_b64) = TaggedBase64::split(tb64)?;
+        let mut value = TaggedBase64::decode_raw(value_b64)?;
+        let checksum = value.pop().ok_or(Tb64Error::MissingChecksum)?;
+        Ok(Dissection {
+            tag,
+            value_b64,
+            checksum_valid: checksum == TaggedBase64::calc_checksum(tag, &value),
+            value,
+            checksum,
+        })
+    }
+
+    /// Checks the structure of a string of the form tag~value without
+    /// decoding it.
+    ///
+    /// This checks the delimiter, the tag, and that the value is plausible
+    /// unpadded base64, but does not verify the checksum. It never returns
+    /// true for a string which [TaggedBase64::parse] rejects on structure,
+    /// so it can be used to reject malformed input cheaply.
+    pub fn looks_valid(tb64: &str) -> bool {
+        let Ok((_, value)) = TaggedBase64::split(tb64) else {
+            return false;
+        };
+        if value.len() % 4 == 1
+            || !value
+                .bytes()
+                .all(|c| TaggedBase64::base64_symbol(c).is_some())
+        {
+            return false;
+        }
+        // The bits of the last symbol which do not fit in a whole byte must
+        // be zero.
+        let unused = match value.len() % 4 {
+            2 => 0b1111,
+            3 => 0b11,
+            _ => 0,
+        };
+        let last = value.as_bytes()[value.len() - 1];
+        TaggedBase64::base64_symbol(last).unwrap_or(0) & unused == 0
+    }
+
+    /// Gets the 6-bit value of a URL-safe base64 symbol.
+    fn base64_symbol(c: u8) -> Option<u8> {
+        match c {
+            b'A'..=b'Z' => Some(c - b'A'),
+            b'a'..=b'z' => Some(c - b'a' + 26),
+            b'0'..=b'9' => Some(c - b'0' + 52),
+            b'-' => Some(62),
+            b'_' => Some(63),
+            _ => None,
+        }
+    }
+
+    /// Constructs a TaggedBase64 from a tag and array of bytes, using
+    /// `policy` rather than the default URL-safe character set to decide
+    /// which characters are allowed in the tag.
+    ///
+    /// Note that [TaggedBase64::is_valid] and [TaggedBase64::set_tag] still
+    /// apply the default policy.
+    pub fn new_with_policy(
+        tag: &str,
+        value: &[u8],
+        policy: &TagPolicy,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        policy.check(tag)?;
+        Ok(TaggedBase64 {
+            tag: tag.into(),
+            value: value.to_vec(),
+            checksum: TaggedBase64::calc_checksum(tag, value),
+        })
+    }
+
+    /// Parses a string of the form tag~value, using `policy` rather than
+    /// the default URL-safe character set to decide which characters are
+    /// allowed in the tag.
+    pub fn parse_with_policy(tb64: &str, policy: &TagPolicy) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split_with_policy(tb64, policy)?;
+        TaggedBase64::decode_checked(tag, value)
+    }
+
+    /// Parses a string of the form tag~value, where the delimiter may have
+    /// been percent-encoded as `%7E` by an aggressive URL encoder.
+    ///
+    /// An already decoded `~` is also accepted.
+    pub fn parse_url(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::parse(&tb64.replace("%7E", "~").replace("%7e", "~"))
+    }
+
+    /// Formats the TaggedBase64 with the delimiter percent-encoded as `%7E`,
+    /// for strict URL consumers which reject a literal `~`.
+    ///
+    /// The tag and value only use URL-safe characters, so nothing else is
+    /// encoded. Read it back with [TaggedBase64::parse_url], which also
+    /// accepts the usual form from [Display](fmt::Display).
+    pub fn to_string_percent_safe(&self) -> String {
+        const ENCODED_DELIM: &str = "%7E";
+        let mut s =
+            String::with_capacity(self.encoded_len() - TB64_DELIM.len_utf8() + ENCODED_DELIM.len());
+        s.push_str(&self.tag);
+        s.push_str(ENCODED_DELIM);
+        self.write_encoded_value(&mut s)
+            .expect("writing to a String does not fail");
+        s
+    }
+
+    /// Formats the TaggedBase64 with `sep` inserted every `group` base64
+    /// characters of the value, e.g. `KEY~AAAA.BBBB.CCCC`, to make long
+    /// values easier to compare by eye. A `group` of zero inserts no
+    /// separators.
+    ///
+    /// The separator may be neither a base64 character nor the delimiter.
+    /// The result can be read with [TaggedBase64::parse_grouped].
+    pub fn to_string_grouped(&self, group: usize, sep: char) -> Result<String, Tb64Error> {
+        TaggedBase64::check_separator(sep)?;
+        let mut s = String::with_capacity(self.encoded_len() + self.encoded_len() / group.max(1));
+        s.push_str(&self.tag);
+        s.push(TB64_DELIM);
+        for (i, c) in self.encode_value().chars().enumerate() {
+            if group > 0 && i > 0 && i % group == 0 {
+                s.push(sep);
+            }
+            s.push(c);
+        }
+        Ok(s)
+    }
+
+    /// Parses a string produced by [TaggedBase64::to_string_grouped],
+    /// ignoring any occurrences of `sep` in the value.
+    pub fn parse_grouped(tb64: &str, sep: char) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::check_separator(sep)?;
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        let value: String = value.chars().filter(|c| *c != sep).collect();
+        TaggedBase64::decode_checked(tag, &value)
+    }
+
+    /// Constructs a TaggedBase64 from a tag and a base64-encoded value,
+    /// for callers which already have the value in base64.
+    ///
+    /// The value is decoded with [TaggedBase64::decode_raw], and must not
+    /// include a checksum: a fresh one is computed, as in
+    /// [TaggedBase64::new].
+    pub fn from_base64(tag: &str, value_base64: &str) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::new(tag, &TaggedBase64::decode_raw(value_base64)?)
+    }
+
+    /// Constructs a TaggedBase64 from a tag and a base64-encoded value
+    /// which does not include a checksum, computing a fresh checksum.
+    ///
+    /// This is a migration helper for data which predates checksumming.
+    /// Since there is no checksum to verify, it provides no guarantee
+    /// about the integrity of the input: corrupted data is silently
+    /// given a valid checksum. It is the same as
+    /// [TaggedBase64::from_base64].
+    pub fn parse_add_checksum(tag: &str, value_base64: &str) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::from_base64(tag, value_base64)
+    }
+
+    /// Recovers the tag of a value which was stored without it, as the
+    /// base64 encoding of the value and checksum, given the tags it might
+    /// have had.
+    ///
+    /// Returns the first candidate for which the checksum matches, or
+    /// `None` if none does or the value is not valid base64. Since the
+    /// checksum covers the tag, the correct tag matches, but with only 8
+    /// bits of checksum a wrong tag also matches about one time in 256, so
+    /// the result is a likely tag, not a certain one.
+    pub fn infer_tag(value_b64_with_checksum: &str, candidates: &[&str]) -> Option<String> {
+        let bytes = TaggedBase64::decode_raw(value_b64_with_checksum).ok()?;
+        let (cs, value) = bytes.split_last()?;
+        candidates
+            .iter()
+            .find(|tag| {
+                TaggedBase64::is_safe_base64_tag(tag)
+                    && *cs == TaggedBase64::calc_checksum(tag, value)
+            })
+            .map(|tag| tag.to_string())
+    }
+
+    /// Recovers what it can from a tag~value string whose value may have
+    /// been truncated, for debugging truncated logs.
+    ///
+    /// Returns the tag and the bytes decoded from the whole groups of four
+    /// base64 symbols in the value, dropping any incomplete group at the
+    /// end. The result is **not verified**: the checksum is not checked,
+    /// and if the string was not truncated, the last byte returned may be
+    /// the checksum rather than part of the value.
+    pub fn parse_partial(tb64: &str) -> Result<(String, Vec<u8>), Tb64Error> {
+        let (tag, value) = tb64
+            .split_once(TB64_DELIM)
+            .ok_or(Tb64Error::MissingDelimiter)?;
+        TaggedBase64::check_tag(tag)?;
+        let whole_groups = &value.as_bytes()[..value.len() - value.len() % 4];
+        Ok((tag.to_string(), BASE64.decode(whole_groups)?))
+    }
+
+    /// Upgrades a string in the legacy tag~value form, which had no
+    /// checksum, to a TaggedBase64 with a freshly computed checksum.
+    ///
+    /// This is for one-time migration of stored data. Like
+    /// [TaggedBase64::parse_add_checksum], it cannot detect corruption of
+    /// the legacy input, which is given a valid checksum regardless. A
+    /// current-format string is not rejected either: its checksum byte is
+    /// taken to be part of the value.
+    pub fn from_legacy(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = tb64
+            .split_once(TB64_DELIM)
+            .ok_or(Tb64Error::MissingDelimiter)?;
+        TaggedBase64::parse_add_checksum(tag, value)
+    }
+
+    /// Parses the base64 value and checksum of a TaggedBase64 whose tag
+    /// was omitted from the wire because it is implied by the context,
+    /// such as the position of a field in a message.
+    ///
+    /// `value_base64` is the part of the string after the delimiter. The
+    /// checksum is verified against the supplied tag.
+    pub fn parse_value_only(tag: &str, value_base64: &str) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::check_tag(tag)?;
+        TaggedBase64::decode_checked(tag, value_base64)
+    }
+
+    /// Constructs a TaggedBase64 from a tag and the canonical serialization
+    /// of `value`.
+    ///
+    /// This is an ad hoc alternative to the [macro@tagged] macro for
+    /// wrapping types which implement
+    /// [CanonicalSerialize](ark_serialize::CanonicalSerialize).
+    #[cfg(feature = "ark-serialize")]
+    pub fn from_canonical<T: CanonicalSerialize>(
+        tag: &str,
+        value: &T,
+        compressed: bool,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        let compress = if compressed {
+            Compress::Yes
+        } else {
+            Compress::No
+        };
+        let mut bytes = Vec::new();
+        value
+            .serialize_with_mode(&mut bytes, compress)
+            .map_err(|_| Tb64Error::InvalidData)?;
+        TaggedBase64::new(tag, &bytes)
+    }
+
+    /// Deserializes the value of a TaggedBase64 using
+    /// [CanonicalDeserialize](ark_serialize::CanonicalDeserialize).
+    ///
+    /// This is the inverse of [TaggedBase64::from_canonical]. The tag is
+    /// not checked.
+    #[cfg(feature = "ark-serialize")]
+    pub fn to_canonical<T: CanonicalDeserialize>(
+        &self,
+        compressed: bool,
+        checked: bool,
+    ) -> Result<T, Tb64Error> {
+        let compress = if compressed {
+            Compress::Yes
+        } else {
+            Compress::No
+        };
+        let validate = if checked { Validate::Yes } else { Validate::No };
+        T::deserialize_with_mode(self.value.as_slice(), compress, validate)
+            .map_err(|_| Tb64Error::InvalidData)
+    }
+
+    /// Formats the TaggedBase64 with a 4-byte CRC32 checksum in place of
+    /// the default single-byte CRC8 checksum.
+    ///
+    /// The larger checksum catches far more corruption in large values,
+    /// such as proofs, at the cost of three extra bytes. The result is
+    /// not accepted by [TaggedBase64::parse]; use
+    /// [TaggedBase64::parse_crc32] instead.
+    pub fn to_string_crc32(&self) -> String {
+        let mut bytes = self.value.clone();
+        bytes.extend_from_slice(&TaggedBase64::calc_crc32(&self.tag, &self.value).to_be_bytes());
+        format!(
+            "{}{}{}",
+            self.tag,
+            TB64_DELIM,
+            TaggedBase64::encode_raw(&bytes)
+        )
+    }
+
+    /// Constructs a TaggedBase64 like [TaggedBase64::new], wrapped so that
+    /// it is formatted with a 4-byte CRC32 checksum.
+    ///
+    /// The default CRC8 form is unaffected; the inner value still displays
+    /// with it.
+    pub fn new_crc32(tag: &str, value: &[u8]) -> Result<TaggedBase64Crc32, Tb64Error> {
+        TaggedBase64::new(tag, value).map(TaggedBase64Crc32)
+    }
+
+    /// Parses a string of the form tag~value produced by
+    /// [TaggedBase64::to_string_crc32], where the last four bytes of the
+    /// decoded value are a CRC32 checksum.
+    ///
+    /// The result is wrapped so that it formats back to the CRC32 form.
+    pub fn parse_crc32(tb64: &str) -> Result<TaggedBase64Crc32, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        let bytes = TaggedBase64::decode_raw(value)?;
+        if bytes.len() < 4 {
+            return Err(Tb64Error::MissingChecksum);
+        }
+        let (value, cs) = bytes.split_at(bytes.len() - 4);
+        if cs == TaggedBase64::calc_crc32(tag, value).to_be_bytes() {
+            TaggedBase64::new_crc32(tag, value)
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Formats the TaggedBase64 with the value and checksum encoded in the
+    /// standard base64 alphabet, with padding, for interop with APIs which
+    /// expect it.
+    ///
+    /// The tag and delimiter are unchanged. The result may contain `+`, `/`
+    /// and `=`, so unlike the usual form it is not URL-safe.
+    pub fn to_string_standard_b64(&self) -> String {
+        let mut bytes = self.value.clone();
+        bytes.push(self.checksum);
+        format!("{}{}{}", self.tag, TB64_DELIM, STANDARD.encode(&bytes))
+    }
+
+    /// Parses a string of the form tag~value produced by
+    /// [TaggedBase64::to_string_standard_b64].
+    pub fn parse_standard_b64(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        let bytes = STANDARD.decode(value)?;
+        let (cs, value) = bytes.split_last().ok_or(Tb64Error::MissingChecksum)?;
+        if *cs == TaggedBase64::calc_checksum(tag, value) {
+            TaggedBase64::new(tag, value)
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Constructs a TaggedBase64 whose value is `value` run-length encoded,
+    /// if that is shorter, for values which would otherwise make long URLs.
+    ///
+    /// The stored value starts with a byte identifying the scheme, which the
+    /// checksum covers like the rest of the value: [COMPRESSION_NONE] if the
+    /// value is stored as is, or [COMPRESSION_RLE] if it is followed by
+    /// pairs of a run length (1 to 255) and a byte. The original value is
+    /// recovered by [TaggedBase64::parse_compressed] or
+    /// [TaggedBase64::decompressed_value].
+    pub fn new_compressed(tag: &str, value: &[u8]) -> Result<TaggedBase64, Tb64Error> {
+        let mut rle = Vec::with_capacity(value.len() + 1);
+        rle.push(COMPRESSION_RLE);
+        for run in value.chunk_by(|a, b| a == b) {
+            for chunk in run.chunks(u8::MAX as usize) {
+                rle.extend_from_slice(&[chunk.len() as u8, chunk[0]]);
+            }
+        }
+        if rle.len() <= value.len() {
+            TaggedBase64::new(tag, &rle)
+        } else {
+            let mut raw = Vec::with_capacity(value.len() + 1);
+            raw.push(COMPRESSION_NONE);
+            raw.extend_from_slice(value);
+            TaggedBase64::new(tag, &raw)
+        }
+    }
+
+    /// Parses a string produced from [TaggedBase64::new_compressed], and
+    /// returns a TaggedBase64 holding the original, decompressed value.
+    ///
+    /// Returns [Tb64Error::InvalidData] if the scheme is unknown or the
+    /// compressed value is malformed.
+    pub fn parse_compressed(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let compressed = TaggedBase64::parse(tb64)?;
+        TaggedBase64::new(&compressed.tag, &compressed.decompressed_value()?)
+    }
+
+    /// Gets the original value of a TaggedBase64 constructed with
+    /// [TaggedBase64::new_compressed].
+    pub fn decompressed_value(&self) -> Result<Vec<u8>, Tb64Error> {
+        match self.value.split_first() {
+            Some((&COMPRESSION_NONE, value)) => Ok(value.to_vec()),
+            Some((&COMPRESSION_RLE, runs)) => {
+                if runs.len() % 2 != 0 {
+                    return Err(Tb64Error::InvalidData);
+                }
+                let mut value = Vec::new();
+                for run in runs.chunks_exact(2) {
+                    if run[0] == 0 {
+                        return Err(Tb64Error::InvalidData);
+                    }
+                    value.resize(value.len() + run[0] as usize, run[1]);
+                }
+                Ok(value)
+            }
+            _ => Err(Tb64Error::InvalidData),
+        }
+    }
+
+    /// Decodes a base64 value whose last byte is the checksum, and checks
+    /// the checksum against `tag`, which must already be known to be safe.
+    fn decode_checked(tag: &str, value: &str) -> Result<TaggedBase64, Tb64Error> {
+        // Base64 decode the value.
+        let bytes = TaggedBase64::decode_raw(value)?;
+        let penultimate = bytes
+            .len()
+            .checked_sub(1)
+            .ok_or(Tb64Error::MissingChecksum)?;
+        let cs = bytes[penultimate];
+        if cs == TaggedBase64::calc_checksum(tag, &bytes[..penultimate]) {
+            Ok(TaggedBase64 {
+                tag: tag.into(),
+                value: bytes[..penultimate].to_vec(),
+                checksum: cs,
+            })
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Writes the base64 encoding of the value followed by the checksum,
+    /// using a fixed buffer on the stack.
+    fn write_encoded_value<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        // Encoding each chunk separately is equivalent to encoding the whole
+        // value, as long as every chunk except the last is a multiple of
+        // three bytes long.
+        const CHUNK: usize = 3 * 64;
+        let mut buf = [0u8; CHUNK / 3 * 4];
+        let mut write_chunk = |w: &mut W, chunk: &[u8]| {
+            let n = BASE64
+                .encode_slice(chunk, &mut buf)
+                .map_err(|_| fmt::Error)?;
+            w.write_str(core::str::from_utf8(&buf[..n]).map_err(|_| fmt::Error)?)
+        };
+
+        let (body, tail) = self.value.split_at(self.value.len() - self.value.len() % 3);
+        for chunk in body.chunks(CHUNK) {
+            write_chunk(w, chunk)?;
+        }
+        let mut last = [0u8; 3];
+        last[..tail.len()].copy_from_slice(tail);
+        last[tail.len()] = self.checksum;
+        write_chunk(w, &last[..tail.len() + 1])
+    }
+
+    /// Returns true if `s` is the canonical string form of this value.
+    ///
+    /// The tag is compared directly, and the value is encoded a chunk at a
+    /// time into a buffer on the stack, so nothing is allocated.
+    fn eq_str(&self, s: &str) -> bool {
+        struct Matcher<'a>(&'a str);
+        impl fmt::Write for Matcher<'_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0 = self.0.strip_prefix(s).ok_or(fmt::Error)?;
+                Ok(())
+            }
+        }
+
+        if s.len() != self.encoded_len() {
+            return false;
+        }
+        let Some(encoded) = s
+            .strip_prefix(&*self.tag)
+            .and_then(|rest| rest.strip_prefix(TB64_DELIM))
+        else {
+            return false;
+        };
+        let mut matcher = Matcher(encoded);
+        self.write_encoded_value(&mut matcher).is_ok() && matcher.0.is_empty()
+    }
+
+    /// Encodes the value followed by the checksum.
+    fn encode_value(&self) -> String {
+        let mut bytes = self.value.clone();
+        bytes.push(self.checksum);
+        TaggedBase64::encode_raw(&bytes)
+    }
+
+    /// Formats the TaggedBase64 with `delim` in place of the usual
+    /// delimiter, for contexts where `~` is a problem.
+    ///
+    /// The delimiter must not be a base64 character. The checksum does not
+    /// depend on the delimiter. The result can be read with
+    /// [TaggedBase64::parse_with_delim].
+    pub fn to_string_with_delim(&self, delim: char) -> Result<String, Tb64Error> {
+        TaggedBase64::check_delim(delim)?;
+        let mut s =
+            String::with_capacity(self.encoded_len() - TB64_DELIM.len_utf8() + delim.len_utf8());
+        s.push_str(&self.tag);
+        s.push(delim);
+        self.write_encoded_value(&mut s)
+            .expect("writing to a String does not fail");
+        Ok(s)
+    }
+
+    /// Parses a string of the form tag, `delim`, value, as produced by
+    /// [TaggedBase64::to_string_with_delim].
+    pub fn parse_with_delim(tb64: &str, delim: char) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::check_delim(delim)?;
+        let (tag, value) = TaggedBase64::split_at_delim(tb64, delim, &TagPolicy::default())?;
+        TaggedBase64::decode_checked(tag, value)
+    }
+
+    /// Formats the TaggedBase64 with a checksum which covers the full
+    /// length of the value.
+    ///
+    /// The default checksum only mixes in the length modulo 256, so it
+    /// gets no help from the length when values differ in length by a
+    /// multiple of 256 bytes. This variant includes all the bytes of the
+    /// length in the CRC instead. It is a different wire format: the
+    /// result can only be read with [TaggedBase64::parse_full_len].
+    pub fn to_string_full_len(&self) -> String {
+        let mut bytes = self.value.clone();
+        bytes.push(TaggedBase64::calc_checksum_full_len(&self.tag, &self.value));
+        format!(
+            "{}{}{}",
+            self.tag,
+            TB64_DELIM,
+            TaggedBase64::encode_raw(&bytes)
+        )
+    }
+
+    /// Parses a string produced by [TaggedBase64::to_string_full_len].
+    pub fn parse_full_len(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        let bytes = TaggedBase64::decode_raw(value)?;
+        let (cs, value) = bytes.split_last().ok_or(Tb64Error::MissingChecksum)?;
+        if *cs == TaggedBase64::calc_checksum_full_len(tag, value) {
+            TaggedBase64::new(tag, value)
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Parses a string of the form tag~value from producers which put the
+    /// checksum byte before the value rather than after it.
+    ///
+    /// The checksum is computed in the usual way; only its position
+    /// differs.
+    pub fn parse_checksum_first(tb64: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        let bytes = TaggedBase64::decode_raw(value)?;
+        let (cs, value) = bytes.split_first().ok_or(Tb64Error::MissingChecksum)?;
+        if *cs == TaggedBase64::calc_checksum(tag, value) {
+            Ok(TaggedBase64 {
+                tag: tag.into(),
+                value: value.to_vec(),
+                checksum: *cs,
+            })
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Formats the TaggedBase64 with a checksum which also covers a context
+    /// string, for domain separation between protocols.
+    ///
+    /// The same tag and value get different checksums in different
+    /// contexts, so a string copied from one protocol into another fails
+    /// to parse with [TaggedBase64::parse_with_context] instead of being
+    /// silently accepted. It is a different wire format: the result can only
+    /// be read with [TaggedBase64::parse_with_context] and the same context.
+    pub fn to_string_with_context(&self, context: &str) -> String {
+        let mut bytes = self.value.clone();
+        bytes.push(TaggedBase64::calc_checksum_with_context(
+            context,
+            &self.tag,
+            &self.value,
+        ));
+        format!(
+            "{}{}{}",
+            self.tag,
+            TB64_DELIM,
+            TaggedBase64::encode_raw(&bytes)
+        )
+    }
+
+    /// Parses a string produced by [TaggedBase64::to_string_with_context],
+    /// verifying the checksum in the given context.
+    pub fn parse_with_context(tb64: &str, context: &str) -> Result<TaggedBase64, Tb64Error> {
+        let (tag, value) = TaggedBase64::split(tb64)?;
+        let bytes = TaggedBase64::decode_raw(value)?;
+        let (cs, value) = bytes.split_last().ok_or(Tb64Error::MissingChecksum)?;
+        if *cs == TaggedBase64::calc_checksum_with_context(context, tag, value) {
+            TaggedBase64::new(tag, value)
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Splits a string of the form tag~value into the tag and the still
+    /// encoded value, checking the tag and that the value is not empty.
+    fn split(tb64: &str) -> Result<(&str, &str), Tb64Error> {
+        TaggedBase64::split_with_policy(tb64, &TagPolicy::default())
+    }
+
+    fn split_with_policy<'a>(
+        tb64: &'a str,
+        policy: &TagPolicy,
+    ) -> Result<(&'a str, &'a str), Tb64Error> {
+        TaggedBase64::split_at_delim(tb64, TB64_DELIM, policy)
+    }
+
+    fn split_at_delim<'a>(
+        tb64: &'a str,
+        delim: char,
+        policy: &TagPolicy,
+    ) -> Result<(&'a str, &'a str), Tb64Error> {
+        // Look for the delimiter, but stop at the first character which
+        // cannot be part of a tag, so that the scan of an invalid string is
+        // bounded by the length of its tag rather than the whole string.
+        // With a custom delimiter, a standard one is skipped over, so that
+        // a string using the wrong delimiter is still reported as missing
+        // the delimiter.
+        let mut delim_pos = None;
+        for (i, c) in tb64.char_indices() {
+            if c == delim {
+                delim_pos = Some(i);
+                break;
+            }
+            if !(policy.is_allowed)(c) && c != TB64_DELIM {
+                // Report the same error as checking the tag would.
+                return Err(policy
+                    .check(&tb64[..i + c.len_utf8()])
+                    .expect_err("the character is not allowed"));
+            }
+        }
+        let delim_pos = delim_pos.ok_or(Tb64Error::MissingDelimiter)?;
+        let (tag, delim_b64) = tb64.split_at(delim_pos);
+        policy.check(tag)?;
+
+        // Remove the delimiter.
+        let value = &delim_b64[delim.len_utf8()..];
+        if value.is_empty() {
+            return Err(Tb64Error::MissingChecksum);
+        }
+        Ok((tag, value))
+    }
+
+    /// Computes the checksum of a tag and value.
+    ///
+    /// The checksum covers the tag as well as the value, so a value moved
+    /// to a different tag is detected. It is a `const fn` so that
+    /// [ConstTaggedBase64::new] can compute it at compile time.
+    const fn calc_checksum(tag: &str, value: &[u8]) -> u8 {
+        let crc = crc8(0, tag.as_bytes());
+        crc8(crc, value) ^ (value.len() as u8)
+    }
+
+    fn calc_checksum_full_len(tag: &str, value: &[u8]) -> u8 {
+        let crc = crc8(crc8(0, tag.as_bytes()), value);
+        crc8(crc, &(value.len() as u64).to_le_bytes())
+    }
+
+    /// The context is followed by the delimiter, which cannot appear in the
+    /// tag, so that different splits of the same bytes between the context
+    /// and the tag give different checksums.
+    fn calc_checksum_with_context(context: &str, tag: &str, value: &[u8]) -> u8 {
+        let crc = crc8(0, context.as_bytes());
+        let crc = crc8(crc, &[TB64_DELIM as u8]);
+        let crc = crc8(crc, tag.as_bytes());
+        crc8(crc, value) ^ (value.len() as u8)
+    }
+
+    fn calc_crc32(tag: &str, value: &[u8]) -> u32 {
+        let mut crc32 = CRC::crc32();
+        crc32.digest(&tag);
+        crc32.digest(&value);
+        (crc32.get_crc() as u32) ^ (value.len() as u32)
+    }
+
+    /// Returns true for characters permitted in URL-safe base64 encoding,
+    /// and false otherwise.
+    pub const fn is_safe_base64_ascii(c: char) -> bool {
+        c.is_ascii_alphanumeric() || (c == '-') || (c == '_')
+    }
+
+    /// Checks that an ASCII byte is safe for use in the tag of a
+    /// TaggedBase64. Because the tags are merely intended to be mnemonic,
+    /// there's no need to support a large and visually ambiguous
+    /// character set.
+    pub const fn is_safe_base64_tag(tag: &str) -> bool {
+        // Iterators aren't available in a const fn. Checking bytes rather
+        // than chars is equivalent, since every byte of a non-ASCII char is
+        // itself non-ASCII.
+        let bytes = tag.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if !TaggedBase64::is_safe_base64_ascii(bytes[i] as char) {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Checks that a tag is safe, reporting the position of the first
+    /// invalid character if it is not.
+    ///
+    /// This is useful for form validation which wants to point out the
+    /// precise problem. The position is a byte offset, which is also the
+    /// character index, since every character before it is ASCII.
+    ///
+    /// This is the same check that constructors and parsers apply, so it
+    /// reports the same error they would.
+    pub fn validate_tag(tag: &str) -> Result<(), Tb64Error> {
+        TagPolicy::default().check(tag)
+    }
+
+    /// Checks that a tag is safe, reporting the first invalid character.
+    fn check_tag(tag: &str) -> Result<(), Tb64Error> {
+        Self::validate_tag(tag)
+    }
+
+    /// Returns true if the tag is safe and the checksum matches the tag
+    /// and value.
+    pub fn is_valid(&self) -> bool {
+        TaggedBase64::is_safe_base64_tag(&self.tag)
+            && self.checksum == TaggedBase64::calc_checksum(&self.tag, &self.value)
+    }
+
+    /// Checks that a character can be used in place of the delimiter
+    /// without being mistaken for part of the tag or value.
+    fn check_delim(delim: char) -> Result<(), Tb64Error> {
+        if TaggedBase64::is_safe_base64_ascii(delim) {
+            Err(Tb64Error::InvalidDelimiter)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that a character can separate groups of base64 characters
+    /// without being mistaken for part of the value or the delimiter.
+    fn check_separator(sep: char) -> Result<(), Tb64Error> {
+        if sep == TB64_DELIM || TaggedBase64::is_safe_base64_ascii(sep) {
+            Err(Tb64Error::InvalidSeparator)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Gets the tag of a TaggedBase64 instance.
+    pub fn tag(&self) -> String {
+        self.tag.to_string()
+    }
+
+    /// Formats the TaggedBase64 with the tag lowercased, for UIs which
+    /// display tags in a normalized case.
+    ///
+    /// The checksum covers the tag, so it is recomputed for the lowercased
+    /// tag and the result parses as a valid TaggedBase64. Note that this
+    /// changes the identity of the value: unless the tag was already
+    /// lowercase, it parses to a value which is not equal to this one.
+    pub fn to_string_lower_tag(&self) -> String {
+        let tag = self.tag.to_ascii_lowercase();
+        TaggedBase64 {
+            checksum: TaggedBase64::calc_checksum(&tag, &self.value),
+            tag: tag.into(),
+            value: self.value.clone(),
+        }
+        .to_string()
+    }
+
+    /// Formats the TaggedBase64 for use as a URL path segment.
+    ///
+    /// This is the canonical string. The tag and the encoding use only
+    /// letters, digits, `-` and `_`, and the delimiter is `~`, all of which
+    /// are unreserved characters in a URL (RFC 3986), so the segment never
+    /// needs percent-encoding and round-trips through any URL library
+    /// unchanged.
+    pub fn to_path_segment(&self) -> String {
+        to_string(self)
+    }
+
+    /// Formats just the tag and delimiter followed by an ellipsis, e.g.
+    /// `TX~…`, for compact logs where only the kind of value matters.
+    pub fn tag_with_ellipsis(&self) -> String {
+        format!("{}{}…", self.tag, TB64_DELIM)
+    }
+
+    /// Computes a six digit fingerprint of the value and checksum, which
+    /// two people can read to each other to check that they have the same
+    /// value.
+    ///
+    /// The fingerprint is deterministic, and different values usually get
+    /// different fingerprints, but it is **not cryptographic**: it is easy
+    /// to find two values with the same fingerprint, so it only guards
+    /// against mistakes, not against an adversary.
+    pub fn fingerprint(&self) -> String {
+        let mut crc32 = CRC::crc32();
+        crc32.digest(&self.value);
+        crc32.digest(&[self.checksum]);
+        format!("{:06}", crc32.get_crc() % 1_000_000)
+    }
+
+    /// Gets the tag of a TaggedBase64 instance as bytes, for comparison
+    /// against byte literals such as `b"KEY"` without cloning.
+    pub fn tag_bytes(&self) -> &[u8] {
+        self.tag.as_bytes()
+    }
+
+    /// Returns true unless the tag is empty.
+    ///
+    /// Empty tags are allowed, but give no hint of what the value is, so
+    /// some code treats such values specially.
+    pub fn has_tag(&self) -> bool {
+        !self.tag.is_empty()
+    }
+
+    /// Sets the tag of a TaggedBase64 instance.
+    pub fn set_tag(&mut self, tag: &str) {
+        assert!(TaggedBase64::is_safe_base64_tag(tag));
+        self.tag = tag.into();
+        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+    }
+
+    /// Gets the value of a TaggedBase64 instance.
+    pub fn value(&self) -> Vec<u8> {
+        self.value.clone()
+    }
+
+    /// Gets the value of a TaggedBase64 instance as a fixed-size array,
+    /// such as a 32-byte key or commitment.
+    ///
+    /// Returns [Tb64Error::WrongLength] if the value is not exactly `N`
+    /// bytes long.
+    pub fn value_array<const N: usize>(&self) -> Result<[u8; N], Tb64Error> {
+        self.value
+            .as_slice()
+            .try_into()
+            .map_err(|_| Tb64Error::WrongLength {
+                expected: N,
+                actual: self.value.len(),
+            })
+    }
+
+    /// Returns true if the values are equal, ignoring the tags and
+    /// checksums.
+    ///
+    /// This is useful for deduplicating payloads which may be wrapped under
+    /// different tags, for example during a migration. Unlike `==`, which
+    /// also requires the tags to match, it treats `OLD~...` and `NEW~...`
+    /// holding the same bytes as equal.
+    pub fn value_eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+
+    /// Returns true if the tags and values are equal, ignoring the
+    /// checksums.
+    ///
+    /// For values built by [TaggedBase64::new] or [TaggedBase64::parse] the
+    /// checksum is derived from the tag and value, so this agrees with `==`.
+    /// The derived `PartialEq` also compares the stored checksum, so the two
+    /// can differ for a value whose checksum was not derived from its
+    /// contents, such as one built from parts with a wrong checksum.
+    pub fn semantic_eq(&self, other: &Self) -> bool {
+        self.tag == other.tag && self.value == other.value
+    }
+
+    /// Computes a hash of the value with `H`, for comparing against a
+    /// stored digest of the value.
+    ///
+    /// Only the value is hashed, not the tag or checksum.
+    #[cfg(feature = "digest")]
+    pub fn value_hash<H: digest::Digest>(&self) -> digest::Output<H> {
+        H::digest(&self.value)
+    }
+
+    /// Sets the value of a TaggedBase64 instance.
+    pub fn set_value(&mut self, value: &[u8]) {
+        self.value = value.to_vec();
+        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+    }
+
+    /// Sets the value of a TaggedBase64 instance, returning the previous
+    /// value.
+    pub fn replace_value(&mut self, value: &[u8]) -> Vec<u8> {
+        let old = core::mem::replace(&mut self.value, value.to_vec());
+        self.checksum = TaggedBase64::calc_checksum(&self.tag, &self.value);
+        old
+    }
+
+    /// Replaces the tag with the result of `f`, for example to add a
+    /// namespace prefix, keeping the value and recomputing the checksum.
+    ///
+    /// Fails if the new tag is not a valid tag.
+    pub fn map_tag(self, f: impl FnOnce(String) -> String) -> Result<TaggedBase64, Tb64Error> {
+        let tag = f(self.tag.to_string());
+        TaggedBase64::check_tag(&tag)?;
+        Ok(TaggedBase64 {
+            checksum: TaggedBase64::calc_checksum(&tag, &self.value),
+            tag: tag.into(),
+            value: self.value,
+        })
+    }
+
+    /// Returns the length of the string representation, without encoding.
+    ///
+    /// This is useful for reserving capacity or checking that a value will
+    /// fit within a URL length limit.
+    pub fn encoded_len(&self) -> usize {
+        // Unpadded base64 uses 4 characters for every 3 bytes, rounded up.
+        let bytes = self.value.len() + 1;
+        self.tag.len() + TB64_DELIM.len_utf8() + (4 * bytes).div_ceil(3)
+    }
+
+    /// Destructures a TaggedBase64 into its tag, value, and checksum
+    /// without cloning the value.
+    ///
+    /// This is the inverse of [TaggedBase64::from_parts].
+    pub fn into_parts(self) -> (String, Vec<u8>, u8) {
+        (self.tag.to_string(), self.value, self.checksum)
+    }
+
+    /// Reconstructs a TaggedBase64 from a tag, value, and checksum, such
+    /// as those returned by [TaggedBase64::into_parts].
+    ///
+    /// The tag is checked and the checksum must match the tag and value.
+    pub fn from_parts(
+        tag: String,
+        value: Vec<u8>,
+        checksum: u8,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        TaggedBase64::check_tag(&tag)?;
+        if checksum == TaggedBase64::calc_checksum(&tag, &value) {
+            Ok(TaggedBase64 {
+                tag: tag.into(),
+                value,
+                checksum,
+            })
+        } else {
+            Err(Tb64Error::InvalidChecksum)
+        }
+    }
+
+    /// Encodes the value alone as base64 and returns it with the checksum,
+    /// for schemas which store the checksum in a separate field.
+    ///
+    /// This is the inverse of [TaggedBase64::from_value_and_checksum].
+    pub fn encode_value_and_checksum(&self) -> (String, u8) {
+        (TaggedBase64::encode_raw(&self.value), self.checksum)
+    }
+
+    /// Reconstructs a TaggedBase64 from a tag, the base64 encoding of the
+    /// value alone, and the checksum, as returned by
+    /// [TaggedBase64::encode_value_and_checksum].
+    ///
+    /// The tag is checked and the checksum must match the tag and value.
+    pub fn from_value_and_checksum(
+        tag: &str,
+        value_base64: &str,
+        checksum: u8,
+    ) -> Result<TaggedBase64, Tb64Error> {
+        let value = TaggedBase64::decode_raw(value_base64)?;
+        TaggedBase64::from_parts(tag.to_string(), value, checksum)
+    }
+
+    /// Wraps the underlying base64 encoder.
+    ///
+    /// Encoding cannot fail, so this never panics. See
+    /// [TaggedBase64::encode_raw_into] for a variant which does not
+    /// allocate.
+    // WASM doesn't support the most general type.
+    //
+    // pub fn encode_raw<T: ?Sized + AsRef<[u8]>>(input: &T) -> String;
+    pub fn encode_raw(input: &[u8]) -> String {
+        BASE64.encode(input)
+    }
+
+    /// Encodes `input` as base64 into the start of `out`, returning the
+    /// number of bytes written.
+    ///
+    /// Returns [Tb64Error::BufferTooSmall] if `out` cannot hold the
+    /// encoding, in which case `out` is not modified.
+    pub fn encode_raw_into(input: &[u8], out: &mut [u8]) -> Result<usize, Tb64Error> {
+        let needed = (4 * input.len()).div_ceil(3);
+        if out.len() < needed {
+            return Err(Tb64Error::BufferTooSmall {
+                needed,
+                available: out.len(),
+            });
+        }
+        Ok(BASE64
+            .encode_slice(input, out)
+            .expect("the buffer is large enough"))
+    }
+    /// Wraps the underlying base64 decoder.
+    pub fn decode_raw(value: &str) -> Result<Vec<u8>, Tb64Error> {
+        Ok(BASE64.decode(value)?)
+    }
+}
+
+impl AsRef<[u8]> for TaggedBase64 {
+    fn as_ref(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+/// Moves the value into a fixed-size array, such as a 32-byte key, if it
+/// has exactly `N` bytes; see also [TaggedBase64::value_array].
+impl<const N: usize> TryFrom<TaggedBase64> for [u8; N] {
+    type Error = Tb64Error;
+
+    fn try_from(tb64: TaggedBase64) -> Result<Self, Tb64Error> {
+        tb64.value
+            .try_into()
+            .map_err(|value: Vec<u8>| Tb64Error::WrongLength {
+                expected: N,
+                actual: value.len(),
+            })
+    }
+}
+
+/// Splits a TaggedBase64 into its tag and value, dropping the checksum,
+/// for code which represents tagged data as a plain tuple.
+impl From<TaggedBase64> for (String, Vec<u8>) {
+    fn from(tb64: TaggedBase64) -> Self {
+        (tb64.tag.to_string(), tb64.value)
+    }
+}
+
+/// Constructs a TaggedBase64 from a tag and value, checking the tag and
+/// computing the checksum as in [TaggedBase64::new].
+impl TryFrom<(String, Vec<u8>)> for TaggedBase64 {
+    type Error = Tb64Error;
+
+    fn try_from((tag, value): (String, Vec<u8>)) -> Result<Self, Tb64Error> {
+        TaggedBase64::check_tag(&tag)?;
+        Ok(TaggedBase64 {
+            checksum: TaggedBase64::calc_checksum(&tag, &value),
+            tag: tag.into(),
+            value,
+        })
+    }
+}
+
+/// Dereferences to the value bytes, not the displayed string, so slice
+/// methods such as `len` and indexing apply to the value.
+impl Deref for TaggedBase64 {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+/// Generates structurally valid values, so that fuzzers exercise more than
+/// the tag and checksum validation.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for TaggedBase64 {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        const TAG_CHARS: &[u8] = URL_SAFE_ALPHABET.as_bytes();
+        let tag = u
+            .arbitrary_iter::<u8>()?
+            .map(|b| b.map(|b| TAG_CHARS[b as usize % TAG_CHARS.len()] as char))
+            .collect::<arbitrary::Result<String>>()?;
+        let value: Vec<u8> = u.arbitrary()?;
+        Ok(TaggedBase64::new(&tag, &value).expect("tag is URL-safe"))
+    }
+}
+
+/// Converts any object that supports the Display trait to a JsValue for
+/// passing to Javascript.
+///
 /// Note: Type parameters aren't supported by `wasm-pack` yet so this
 /// can't be included in the TaggedBase64 type implementation.
 #[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
@@ -459,6 +2645,34 @@ impl JsTaggedBase64 {
         Ok(result)
     }
 
+    /// Parses a string of the form tag~value, where `delim`, which must be
+    /// a single character which is not base64, is used in place of `~`.
+    pub fn parse_with_delim(tb64: &str, delim: &str) -> Result<TaggedBase64, JsValue> {
+        Ok(TaggedBase64::parse_with_delim(tb64, js_delim(delim)?)?)
+    }
+
+    /// Formats the JsTaggedBase64 instance with `delim`, which must be a
+    /// single character which is not base64, in place of `~`.
+    pub fn to_string_with_delim(&self, delim: &str) -> Result<String, JsValue> {
+        Ok(self.tb64.to_string_with_delim(js_delim(delim)?)?)
+    }
+}
+
+/// Converts a delimiter passed from JavaScript as a string to a `char`.
+#[cfg(all(target_arch = "wasm32", feature = "wasm-bindgen"))]
+fn js_delim(delim: &str) -> Result<char, Tb64Error> {
+    let mut chars = delim.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(Tb64Error::InvalidDelimiter),
+    }
+}
+
+// The methods which do not involve `JsValue` are also available natively, so
+// that the wrapper logic can be tested without a browser.
+#[cfg(feature = "wasm-bindgen")]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl JsTaggedBase64 {
     /// Gets the tag of a TaggedBase64 instance.
     pub fn tag(&self) -> String {
         TaggedBase64::tag(&self.tb64)
@@ -499,4 +2713,13 @@ impl JsTaggedBase64 {
 /// derives appropriate serde implementations for serializing as an opaque blob.
 pub trait Tagged {
     fn tag() -> String;
+
+    /// The length in bytes of the serialized value, if it is always the
+    /// same.
+    ///
+    /// The [macro@tagged] macro sets this with the `len = N` flag, and then
+    /// rejects an empty value with [Tb64Error::WrongLength].
+    fn expected_len() -> Option<usize> {
+        None
+    }
 }
@@ -1,8 +1,8 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use std::io;
 use std::io::{Read, Write};
 use std::process::exit;
-use tagged_base64::TaggedBase64;
+use tagged_base64::{TaggedBase64, TB64_DELIM};
 
 #[derive(Parser)]
 #[command(
@@ -28,12 +28,44 @@ pub struct MainOpt {
     ///    `cat adventure.bin | tagged_base64 --tag ADVENTURE`
     #[arg(long = "tag")]
     pub tag: Option<String>,
+
+    /// Name of an environment variable holding the tag for raw data from
+    /// stdin, so that the tag does not appear in process listings.
+    ///
+    ///    `TAG=ADVENTURE tagged_base64 --tag-env TAG < adventure.bin`
+    #[arg(long = "tag-env", value_name = "VARNAME", conflicts_with = "tag")]
+    pub tag_env: Option<String>,
+
+    /// Checksum algorithm to use when encoding and to expect when decoding.
+    ///
+    /// `none` reads and writes the legacy format without a checksum.
+    #[arg(long = "checksum", value_enum, default_value_t = Checksum::Crc8)]
+    pub checksum: Checksum,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Checksum {
+    /// The standard 8-bit checksum.
+    Crc8,
+    /// A 32-bit checksum; see `TaggedBase64::to_string_crc32`.
+    Crc32,
+    /// No checksum, as in the legacy format.
+    None,
 }
 
 fn main() {
     let parsed = MainOpt::parse();
     let tb64 = &parsed.tb64_str;
-    let tag = &parsed.tag;
+    let tag = match &parsed.tag_env {
+        Some(var) => match std::env::var(var) {
+            Ok(tag) => Some(tag),
+            Err(e) => {
+                eprintln!("Error: {}: {}", var, e);
+                exit(1);
+            }
+        },
+        None => parsed.tag.clone(),
+    };
     if tb64.is_some() == tag.is_some() {
         println!(
             "tagged_base64: one argument required\n\
@@ -41,7 +73,12 @@ fn main() {
         );
         exit(2);
     } else if let Some(tb64_str) = &parsed.tb64_str {
-        match TaggedBase64::parse(tb64_str) {
+        let result = match parsed.checksum {
+            Checksum::Crc8 => TaggedBase64::parse(tb64_str),
+            Checksum::Crc32 => TaggedBase64::parse_crc32(tb64_str).map(TaggedBase64::from),
+            Checksum::None => TaggedBase64::from_legacy(tb64_str),
+        };
+        match result {
             Ok(v) => {
                 io::stdout().write_all(&v.value()).unwrap();
                 exit(0);
@@ -51,10 +88,15 @@ fn main() {
                 exit(1);
             }
         };
-    } else if let Some(tag) = &parsed.tag {
+    } else if let Some(tag) = &tag {
         let mut v = Vec::new();
         io::stdin().read_to_end(&mut v).unwrap();
-        println!("{}", TaggedBase64::new(tag, &v).unwrap());
+        let tb64 = TaggedBase64::new(tag, &v).unwrap();
+        match parsed.checksum {
+            Checksum::Crc8 => println!("{}", tb64),
+            Checksum::Crc32 => println!("{}", tb64.to_string_crc32()),
+            Checksum::None => println!("{}{}{}", tag, TB64_DELIM, TaggedBase64::encode_raw(&v)),
+        }
         exit(0);
     }
 }
@@ -0,0 +1,43 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! JSON Schema descriptors for TaggedBase64 strings, so that API
+//! documentation generators such as OpenAPI tools can describe tagged
+//! fields accurately.
+
+use crate::{Tagged, TaggedBase64, Tb64Error, TB64_DELIM};
+use ark_std::format;
+use serde_json::{json, Value};
+
+/// Describes the strings which encode values with the given tag.
+///
+/// The schema is a string with a pattern matching the tag, the delimiter,
+/// and a base64 value. If `expected_len` is given, the value has exactly
+/// that many bytes, which also fixes the length of the string.
+///
+/// Fails if `tag` is not a valid tag, as no string could match the schema.
+pub fn json_schema(tag: &str, expected_len: Option<usize>) -> Result<Value, Tb64Error> {
+    // Safe tags only contain characters which are literal in a regex.
+    TaggedBase64::validate_tag(tag)?;
+    let mut schema = json!({
+        "type": "string",
+        "pattern": format!("^{}{}[A-Za-z0-9_-]+$", tag, TB64_DELIM),
+    });
+    if let Some(len) = expected_len {
+        let string_len = tag.len() + TB64_DELIM.len_utf8() + (4 * (len + 1)).div_ceil(3);
+        schema["minLength"] = json!(string_len);
+        schema["maxLength"] = json!(string_len);
+        schema["description"] = json!(format!(
+            "Tagged base64 with tag {:?} and a {} byte value",
+            tag, len
+        ));
+    } else {
+        schema["description"] = json!(format!("Tagged base64 with tag {:?}", tag));
+    }
+    Ok(schema)
+}
+
+/// Describes the strings which encode a [Tagged] type, using its tag and
+/// [expected length](Tagged::expected_len).
+pub fn json_schema_for<T: Tagged>() -> Result<Value, Tb64Error> {
+    json_schema(&T::tag(), T::expected_len())
+}
@@ -0,0 +1,99 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! An [io::Write](std::io::Write) adapter which produces a TaggedBase64
+//! string from bytes written to it, so that serializers can write directly
+//! into the encoding.
+
+extern crate std;
+
+use crate::{crc8, TaggedBase64, Tb64Error, BASE64, TB64_DELIM};
+use ark_std::string::String;
+use base64::Engine;
+use std::io;
+
+/// Encodes bytes as they are written, and produces the tagged string with
+/// its checksum on [finish](TaggedBase64Writer::finish).
+///
+/// The value is encoded incrementally and the checksum is computed as it
+/// goes, so the value is never held in memory in full.
+///
+/// ```
+/// use std::io::Write;
+/// use tagged_base64::{writer::TaggedBase64Writer, TaggedBase64};
+///
+/// let mut writer = TaggedBase64Writer::new("TX").unwrap();
+/// writer.write_all(b"some ").unwrap();
+/// writer.write_all(b"bytes").unwrap();
+/// assert_eq!(
+///     writer.finish(),
+///     TaggedBase64::new("TX", b"some bytes").unwrap().to_string()
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct TaggedBase64Writer {
+    /// The tag, delimiter, and encoding of the whole groups written so far.
+    out: String,
+    /// The CRC of the tag and the bytes written so far.
+    crc: u8,
+    len: usize,
+    /// Bytes which do not yet make a whole group of 3.
+    pending: [u8; 3],
+    pending_len: usize,
+}
+
+impl TaggedBase64Writer {
+    /// Constructs a writer for a value with the given tag.
+    pub fn new(tag: &str) -> Result<Self, Tb64Error> {
+        TaggedBase64::check_tag(tag)?;
+        let mut out = String::from(tag);
+        out.push(TB64_DELIM);
+        Ok(Self {
+            out,
+            crc: crc8(0, tag.as_bytes()),
+            len: 0,
+            pending: [0; 3],
+            pending_len: 0,
+        })
+    }
+
+    /// Appends the checksum and returns the TaggedBase64 string.
+    pub fn finish(mut self) -> String {
+        let checksum = self.crc ^ (self.len as u8);
+        self.pending[self.pending_len] = checksum;
+        BASE64.encode_string(&self.pending[..self.pending_len + 1], &mut self.out);
+        self.out
+    }
+}
+
+impl io::Write for TaggedBase64Writer {
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        let written = buf.len();
+        self.crc = crc8(self.crc, buf);
+        self.len += buf.len();
+
+        // Complete a pending group first.
+        if self.pending_len > 0 {
+            let n = buf.len().min(3 - self.pending_len);
+            self.pending[self.pending_len..self.pending_len + n].copy_from_slice(&buf[..n]);
+            self.pending_len += n;
+            buf = &buf[n..];
+            if self.pending_len < 3 {
+                return Ok(written);
+            }
+            BASE64.encode_string(self.pending, &mut self.out);
+            self.pending_len = 0;
+        }
+
+        // Encode the whole groups and keep the rest.
+        let whole = buf.len() - buf.len() % 3;
+        BASE64.encode_string(&buf[..whole], &mut self.out);
+        let rest = &buf[whole..];
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_len = rest.len();
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+
+//! Pins the number of allocations made by parsing and formatting, so that a
+//! change which adds a clone is caught.
+//!
+//! This is a separate test binary because it installs a counting global
+//! allocator. Allocations are counted per thread, so tests running in
+//! parallel do not disturb each other.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use tagged_base64::TaggedBase64;
+
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // The counter may already be destroyed while a thread exits.
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Returns the result of `f` and the number of allocations it made.
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let res = f();
+    (res, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn test_parse_allocations() {
+    let s = TaggedBase64::new("KEY", b"a small value")
+        .unwrap()
+        .to_string();
+
+    // One for the decoded bytes, one for the tag and one for the value.
+    let (tb64, n) = count_allocations(|| TaggedBase64::parse(&s).unwrap());
+    assert!(n <= 3, "parse made {n} allocations");
+
+    // Checking the string alone does not allocate at all.
+    let ((), n) = count_allocations(|| TaggedBase64::verify_str(&s).unwrap());
+    assert_eq!(n, 0, "verify_str made {n} allocations");
+
+    // Neither does comparing a value with its string form.
+    let (eq, n) = count_allocations(|| *s.as_str() == tb64);
+    assert!(eq);
+    assert_eq!(n, 0, "comparing with a string made {n} allocations");
+
+    // Formatting allocates the string once, at its final length.
+    let (_, n) = count_allocations(|| tagged_base64::to_string(&tb64));
+    assert_eq!(n, 1, "to_string made {n} allocations");
+
+    // Cloning shares the tag, where atomics make that possible, so only the
+    // value is copied.
+    let expected = if cfg!(target_has_atomic = "ptr") {
+        1
+    } else {
+        2
+    };
+    let (_, n) = count_allocations(|| tb64.clone());
+    assert_eq!(n, expected, "clone made {n} allocations");
+
+    // Parsing into a reused buffer of sufficient capacity does not allocate.
+    let mut buf = Vec::with_capacity(64);
+    let (_, n) = count_allocations(|| TaggedBase64::parse_reuse(&s, &mut buf).unwrap());
+    assert_eq!(n, 0, "parse_reuse made {n} allocations");
+}
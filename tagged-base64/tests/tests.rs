@@ -203,12 +203,12 @@ fn tagged_base64_parse() {
     );
 
     // All the following have invalid characters in the tag.
-    assert!(TaggedBase64::new("~", b"").is_err());
-    assert!(TaggedBase64::new("a~", b"").is_err());
-    assert!(TaggedBase64::new("~b", b"").is_err());
-    assert!(TaggedBase64::new("c~d", b"").is_err());
-    assert!(TaggedBase64::new("e~f~", b"").is_err());
-    assert!(TaggedBase64::new("g~h~i", b"").is_err());
+    for tag in ["~", "a~", "~b", "c~d", "e~f~", "g~h~i"] {
+        assert!(matches!(
+            TaggedBase64::new(tag, b"").unwrap_err(),
+            Tb64Error::TagContainsDelimiter
+        ));
+    }
     assert!(TaggedBase64::new("Oh, no!", b"").is_err());
     assert!(TaggedBase64::new("Σ", b"").is_err());
 
@@ -356,7 +356,12 @@ fn test_empty_value() {
 #[wasm_bindgen_test]
 fn test_js_new_error() {
     match JsTaggedBase64::new("~", b"oops!") {
-        Err(e) => assert_eq!(e, to_jsvalue("An invalid character was found in the tag.")),
+        Err(e) => assert_eq!(
+            e,
+            to_jsvalue(
+                "The tag contains the delimiter '~'. Was a whole tag~value string passed as the tag?"
+            )
+        ),
         _ => assert!(false),
     }
 }
@@ -379,13 +384,34 @@ fn test_error_fmt() {
         ),
         "invalid base 64: Invalid symbol 42, offset 66."
     );
+    assert_eq!(
+        format!(
+            "{}",
+            Tb64Error::from(base64::DecodeError::InvalidLastSymbol(2, 70))
+        ),
+        "invalid base 64: Invalid last symbol 70, offset 2."
+    );
+    assert_eq!(
+        format!("{}", Tb64Error::from(base64::DecodeError::InvalidLength(5))),
+        "invalid base 64: Invalid input length: 5"
+    );
 }
 
 #[test]
 fn basic_errors() {
     let e = TaggedBase64::new("A/A", &[0]).unwrap_err();
     println!("{:?}: {}", e, e);
-    assert!(matches!(e, Tb64Error::InvalidTag));
+    assert!(matches!(
+        e,
+        Tb64Error::InvalidTag {
+            position: 1,
+            character: '/'
+        }
+    ));
+
+    let e = TaggedBase64::new("TX~dHJhbnNhY3Rpb24C", &[0]).unwrap_err();
+    println!("{:?}: {}", e, e);
+    assert!(matches!(e, Tb64Error::TagContainsDelimiter));
 
     let e = TaggedBase64::parse("AA").unwrap_err();
     println!("{:?}: {}", e, e);
@@ -393,7 +419,13 @@ fn basic_errors() {
 
     let e = TaggedBase64::parse("AAA~A/A").unwrap_err();
     println!("{:?}: {}", e, e);
-    assert!(matches!(e, Tb64Error::Base64 { .. }));
+    assert!(matches!(
+        e,
+        Tb64Error::InvalidByte {
+            offset: 1,
+            byte: b'/'
+        }
+    ));
 
     let e = TaggedBase64::parse("AAA~AAA").unwrap_err();
     println!("{:?}: {}", e, e);
@@ -409,7 +441,13 @@ fn basic_errors() {
 
     let e = TaggedBase64::parse("AAA~AAF").unwrap_err();
     println!("{:?}: {}", e, e);
-    assert!(matches!(e, Tb64Error::Base64 { .. }));
+    assert!(matches!(
+        e,
+        Tb64Error::InvalidLastSymbol {
+            offset: 2,
+            byte: b'F'
+        }
+    ));
 }
 
 fn one_bit_corruption(tag: u16, data: (Vec<u8>, u8), bit_to_flip: u16) {
@@ -434,6 +472,66 @@ fn one_bit_corruption_quickcheck(tag: u16, data: (Vec<u8>, u8), bit_to_flip: u16
     one_bit_corruption(tag, data, bit_to_flip);
 }
 
+/// A value of several kilobytes, much longer than the vectors quickcheck
+/// generates by default, so that the length is not a single byte.
+#[derive(Clone, Debug)]
+struct BigValue(Vec<u8>);
+
+impl quickcheck::Arbitrary for BigValue {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        let len = 1024 + usize::arbitrary(g) % (8 * 1024);
+        BigValue((0..len).map(|_| u8::arbitrary(g)).collect())
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        Box::new(self.0.shrink().filter(|v| v.len() >= 1024).map(BigValue))
+    }
+}
+
+/// Flips one bit of the encoding of a large value. Every single-bit flip
+/// which leaves valid UTF-8 must be detected, either as malformed base64 or
+/// as a checksum mismatch.
+fn big_value_one_bit_corruption(tag: u16, value: BigValue, bit_to_flip: u32) {
+    let encoded_tag = TaggedBase64::encode_raw(&tag.to_le_bytes());
+    let encoded = TaggedBase64::new(&encoded_tag, &value.0).unwrap();
+    let mut encoded_bytes = to_string(&encoded).into_bytes();
+    let bit = bit_to_flip as usize % (encoded_bytes.len() * 8);
+    encoded_bytes[bit / 8] ^= 1 << (bit % 8);
+    if let Ok(corrupted) = str::from_utf8(&encoded_bytes) {
+        assert!(
+            TaggedBase64::parse(corrupted).is_err(),
+            "undetected flip of bit {bit} in a value of {} bytes",
+            value.0.len()
+        );
+    }
+}
+
+#[quickcheck]
+fn big_value_one_bit_corruption_quickcheck(tag: u16, value: BigValue, bit_to_flip: u32) {
+    big_value_one_bit_corruption(tag, value, bit_to_flip);
+}
+
+/// Flips every bit of the value part of one large encoding, including those
+/// in the last group, which mixes the end of the value with the checksum.
+#[test]
+fn test_big_value_every_bit_flip() {
+    let value: Vec<u8> = (0..3000u32).map(|i| (i * 31 + i / 7) as u8).collect();
+    for len in [1024, 1025, 1026, 3000] {
+        let encoded = TaggedBase64::new("BIG", &value[..len]).unwrap().to_string();
+        let start = "BIG~".len();
+        for bit in start * 8..encoded.len() * 8 {
+            let mut bytes = encoded.clone().into_bytes();
+            bytes[bit / 8] ^= 1 << (bit % 8);
+            if let Ok(corrupted) = str::from_utf8(&bytes) {
+                assert!(
+                    TaggedBase64::parse(corrupted).is_err(),
+                    "undetected flip of bit {bit} with {len} bytes"
+                );
+            }
+        }
+    }
+}
+
 #[tagged("BLOB")]
 #[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
 struct Blob(Vec<u8>);
@@ -560,6 +658,12 @@ fn test_tagged() {
     assert_eq!(b, t.try_into().unwrap());
 }
 
+#[test]
+fn test_tagged_unexpected_tag() {
+    let t = TaggedBase64::new("OTHER", &[1, 2, 3]).unwrap();
+    assert!(matches!(Blob::try_from(t), Err(Tb64Error::UnexpectedTag)));
+}
+
 #[test]
 fn test_serde_json_str() {
     let bytes = (0..100).collect::<Vec<_>>();
@@ -616,3 +720,3128 @@ fn test_compat() {
     );
     assert_eq!(tb64, expected.parse().unwrap());
 }
+
+fn crc32_round_trip() {
+    for value in [&b""[..], b"f", b"foobar", &[0xff; 1000]] {
+        let tb64 = TaggedBase64::new("PROOF", value).unwrap();
+        let s = tb64.to_string_crc32();
+        assert!(s.starts_with("PROOF~"));
+        // Four checksum bytes instead of one.
+        assert_eq!(
+            TaggedBase64::decode_raw(&s["PROOF~".len()..])
+                .unwrap()
+                .len(),
+            value.len() + 4
+        );
+        assert_eq!(TaggedBase64::parse_crc32(&s).unwrap().0, tb64);
+
+        // The CRC32 form survives a round trip.
+        let crc32 = TaggedBase64::new_crc32("PROOF", value).unwrap();
+        assert_eq!(crc32.0, tb64);
+        assert_eq!(crc32.to_string(), s);
+        assert_eq!(TaggedBase64::parse_crc32(&s).unwrap().to_string(), s);
+        assert_eq!(s.parse::<TaggedBase64Crc32>().unwrap(), crc32);
+        // The inner value keeps the default form.
+        assert_eq!(TaggedBase64::from(crc32).to_string(), tb64.to_string());
+    }
+    assert!(matches!(
+        TaggedBase64::new_crc32("P~", b""),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+
+    // The default parser does not accept the CRC32 form, and vice versa.
+    let tb64 = TaggedBase64::new("PROOF", b"foobar").unwrap();
+    assert!(TaggedBase64::parse(&tb64.to_string_crc32()).is_err());
+    assert!(TaggedBase64::parse_crc32(&tb64.to_string()).is_err());
+
+    // Too short to hold a checksum.
+    for s in ["A~AAA", "TAG~AAA", "TAG~AA"] {
+        assert!(matches!(
+            TaggedBase64::parse_crc32(s).unwrap_err(),
+            Tb64Error::MissingChecksum
+        ));
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn wasm_crc32_round_trip() {
+    crc32_round_trip();
+}
+
+#[test]
+fn test_crc32_round_trip() {
+    crc32_round_trip();
+}
+
+#[quickcheck]
+fn crc32_one_bit_corruption_quickcheck(data: Vec<u8>, bit_to_flip: u16) {
+    // Flip one bit of the decoded value or checksum and re-encode, so the
+    // corruption is never masked by a base64 decoding error.
+    let encoded = TaggedBase64::new("PROOF", &data).unwrap().to_string_crc32();
+    let mut bytes = TaggedBase64::decode_raw(&encoded["PROOF~".len()..]).unwrap();
+    let ix = (bit_to_flip >> 3) as usize % bytes.len();
+    bytes[ix] ^= 1 << (bit_to_flip & 7);
+    let corrupted = format!("PROOF~{}", TaggedBase64::encode_raw(&bytes));
+    assert!(matches!(
+        TaggedBase64::parse_crc32(&corrupted).unwrap_err(),
+        Tb64Error::InvalidChecksum
+    ));
+}
+
+#[test]
+fn test_parse_add_checksum() {
+    // "Zm9vYmFy" is the base64 encoding of "foobar", without a checksum.
+    let tb64 = TaggedBase64::parse_add_checksum("LEGACY", "Zm9vYmFy").unwrap();
+    assert_eq!(tb64.tag(), "LEGACY");
+    assert_eq!(tb64.value(), b"foobar");
+
+    // Re-serializing yields a string with a valid checksum.
+    let s = tb64.to_string();
+    assert_eq!(TaggedBase64::parse(&s).unwrap(), tb64);
+    assert_eq!(tb64, TaggedBase64::new("LEGACY", b"foobar").unwrap());
+
+    // An empty value is fine, since no checksum is expected.
+    assert_eq!(
+        TaggedBase64::parse_add_checksum("A", "").unwrap(),
+        TaggedBase64::new("A", b"").unwrap()
+    );
+
+    assert!(matches!(
+        TaggedBase64::parse_add_checksum("A/A", "Zm9vYmFy").unwrap_err(),
+        Tb64Error::InvalidTag {
+            position: 1,
+            character: '/'
+        }
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_add_checksum("A", "Zm9vYmF").unwrap_err(),
+        Tb64Error::InvalidLastSymbol { .. }
+    ));
+}
+
+#[test]
+fn test_tag_from_str() {
+    let tag: Tag = "KEY".parse().unwrap();
+    assert_eq!(tag.as_str(), "KEY");
+    assert_eq!(tag.to_string(), "KEY");
+    assert_eq!(tag, Tag::new("KEY").unwrap());
+    assert_eq!("".parse::<Tag>().unwrap().as_str(), "");
+    assert_eq!(
+        "abcXYZ-_0123456789".parse::<Tag>().unwrap().as_str(),
+        "abcXYZ-_0123456789"
+    );
+
+    assert!(matches!(
+        "Oh, no!".parse::<Tag>().unwrap_err(),
+        Tb64Error::InvalidTag {
+            position: 2,
+            character: ','
+        }
+    ));
+    assert!(matches!(
+        "Σ".parse::<Tag>().unwrap_err(),
+        Tb64Error::NonAsciiTag {
+            position: 0,
+            character: 'Σ'
+        }
+    ));
+    assert!(matches!(
+        "KEY~".parse::<Tag>().unwrap_err(),
+        Tb64Error::TagContainsDelimiter
+    ));
+}
+
+#[derive(serde::Deserialize)]
+struct TestVector {
+    tag: String,
+    value_hex: String,
+    expected: String,
+}
+
+/// Checks the canonical test vectors in `test_vectors.json`.
+///
+/// These pin the exact output format and checksum, so that ports to other
+/// languages can be checked against the same fixtures and any change to the
+/// format is caught.
+#[test]
+fn test_vectors() {
+    let vectors: Vec<TestVector> = serde_json::from_str(include_str!("test_vectors.json")).unwrap();
+    assert!(!vectors.is_empty());
+    for v in vectors {
+        let value = (0..v.value_hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&v.value_hex[i..i + 2], 16).unwrap())
+            .collect::<Vec<_>>();
+        let tb64 = TaggedBase64::new(&v.tag, &value).unwrap();
+        assert_eq!(tb64.to_string(), v.expected, "tag {:?}", v.tag);
+        assert_eq!(TaggedBase64::parse(&v.expected).unwrap(), tb64);
+    }
+}
+
+#[test]
+fn test_canonical() {
+    use ark_std::UniformRand;
+    use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+
+    let x = ark_bls12_381::Fr::rand(&mut ChaChaRng::from_seed([42; 32]));
+    for compressed in [false, true] {
+        let tb64 = TaggedBase64::from_canonical("FR", &x, compressed).unwrap();
+        assert_eq!(tb64.tag(), "FR");
+        for checked in [false, true] {
+            let y: ark_bls12_381::Fr = tb64.to_canonical(compressed, checked).unwrap();
+            assert_eq!(x, y);
+        }
+    }
+
+    let g = ark_bls12_381::G1Affine::rand(&mut ChaChaRng::from_seed([42; 32]));
+    let compressed = TaggedBase64::from_canonical("G1", &g, true).unwrap();
+    let uncompressed = TaggedBase64::from_canonical("G1", &g, false).unwrap();
+    assert!(compressed.value().len() < uncompressed.value().len());
+    assert_eq!(
+        g,
+        compressed
+            .to_canonical::<ark_bls12_381::G1Affine>(true, true)
+            .unwrap()
+    );
+    assert!(matches!(
+        compressed
+            .to_canonical::<ark_bls12_381::G1Affine>(false, true)
+            .unwrap_err(),
+        Tb64Error::InvalidData
+    ));
+
+    assert!(matches!(
+        TaggedBase64::from_canonical("F~R", &x, true).unwrap_err(),
+        Tb64Error::TagContainsDelimiter
+    ));
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn test_arbitrary() {
+    use arbitrary::{Arbitrary, Unstructured};
+    use rand_chacha::{
+        rand_core::{RngCore, SeedableRng},
+        ChaChaRng,
+    };
+
+    let mut rng = ChaChaRng::from_seed([42; 32]);
+    for len in 0..200 {
+        let mut data = vec![0u8; len];
+        rng.fill_bytes(&mut data);
+        let tb64 = TaggedBase64::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        assert!(tb64.is_valid());
+        assert_eq!(TaggedBase64::parse(&tb64.to_string()).unwrap(), tb64);
+    }
+}
+
+#[test]
+fn test_parse_url() {
+    let tb64 = TaggedBase64::new("TX", b"transaction identifier goes here").unwrap();
+    let plain = tb64.to_string();
+    let encoded = tb64.to_string_percent_safe();
+    assert_eq!(encoded, tb64.to_string().replace('~', "%7E"));
+    assert!(!encoded.contains('~'));
+
+    assert_eq!(TaggedBase64::parse_url(&plain).unwrap(), tb64);
+    assert_eq!(TaggedBase64::parse_url(&encoded).unwrap(), tb64);
+    assert_eq!(
+        TaggedBase64::parse_url(&encoded.replace("%7E", "%7e")).unwrap(),
+        tb64
+    );
+
+    // The empty tag works too.
+    let untagged = TaggedBase64::new("", b"abc").unwrap();
+    assert_eq!(
+        TaggedBase64::parse_url(&untagged.to_string_percent_safe()).unwrap(),
+        untagged
+    );
+
+    // The strict parser does not decode the delimiter.
+    assert!(TaggedBase64::parse(&encoded).is_err());
+    assert!(TaggedBase64::parse_url("TX%7F").is_err());
+}
+
+#[test]
+fn test_into_parts() {
+    let tb64 = TaggedBase64::new("KEY", b"public key bits").unwrap();
+    let s = tb64.to_string();
+    let (tag, value, checksum) = tb64.clone().into_parts();
+    assert_eq!(tag, "KEY");
+    assert_eq!(value, b"public key bits");
+
+    // The checksum is the last byte of the encoded value.
+    let bytes = TaggedBase64::decode_raw(&s["KEY~".len()..]).unwrap();
+    assert_eq!(checksum, *bytes.last().unwrap());
+
+    assert_eq!(TaggedBase64::new(&tag, &value).unwrap(), tb64);
+    assert_eq!(
+        TaggedBase64::from_parts(tag, value, checksum).unwrap(),
+        tb64
+    );
+}
+
+#[test]
+fn test_from_parts() {
+    let (tag, value, checksum) = TaggedBase64::new("TX", b"transaction")
+        .unwrap()
+        .into_parts();
+    assert!(matches!(
+        TaggedBase64::from_parts(tag.clone(), value.clone(), checksum.wrapping_add(1)).unwrap_err(),
+        Tb64Error::InvalidChecksum
+    ));
+    assert!(matches!(
+        TaggedBase64::from_parts("TX".into(), b"transactioN".to_vec(), checksum).unwrap_err(),
+        Tb64Error::InvalidChecksum
+    ));
+    assert!(matches!(
+        TaggedBase64::from_parts("T/X".into(), value.clone(), checksum).unwrap_err(),
+        Tb64Error::InvalidTag {
+            position: 1,
+            character: '/'
+        }
+    ));
+
+    let tb64 = TaggedBase64::from_parts(tag, value, checksum).unwrap();
+    assert!(tb64.is_valid());
+    assert_eq!(tb64, TaggedBase64::new("TX", b"transaction").unwrap());
+}
+
+#[test]
+fn test_abbreviated() {
+    let mut abbreviations = TagAbbreviations::new();
+    abbreviations.insert("INTERNAL_ASSET_CODE", "IAC").unwrap();
+    abbreviations.insert("RECORD_OPENING", "RO").unwrap();
+    assert_eq!(abbreviations.abbreviate("INTERNAL_ASSET_CODE"), "IAC");
+    assert_eq!(abbreviations.expand("IAC"), "INTERNAL_ASSET_CODE");
+    assert_eq!(abbreviations.abbreviate("OTHER"), "OTHER");
+    assert_eq!(abbreviations.expand("OTHER"), "OTHER");
+
+    let tb64 = TaggedBase64::new("INTERNAL_ASSET_CODE", b"asset code bits").unwrap();
+    let s = tb64.to_string_abbreviated(&abbreviations);
+    assert!(s.starts_with("IAC~"));
+    assert!(s.len() < tb64.to_string().len());
+    let parsed = TaggedBase64::parse_abbreviated(&s, &abbreviations).unwrap();
+    assert_eq!(parsed.tag(), "INTERNAL_ASSET_CODE");
+    assert_eq!(parsed, tb64);
+
+    // The checksum covers the full tag, so the plain parser rejects the
+    // abbreviated form.
+    assert!(matches!(
+        TaggedBase64::parse(&s).unwrap_err(),
+        Tb64Error::InvalidChecksum
+    ));
+
+    // Tags without an abbreviation pass through unchanged.
+    let other = TaggedBase64::new("OTHER", b"bits").unwrap();
+    assert_eq!(
+        other.to_string_abbreviated(&abbreviations),
+        other.to_string()
+    );
+    assert_eq!(
+        TaggedBase64::parse_abbreviated(&other.to_string(), &abbreviations).unwrap(),
+        other
+    );
+
+    // Conflicting and invalid entries are rejected.
+    for (tag, abbreviation) in [
+        ("INTERNAL_ASSET_CODE", "I"),
+        ("LONG_TAG", "IAC"),
+        ("IAC", "X"),
+        ("LONG_TAG", "RECORD_OPENING"),
+    ] {
+        assert!(matches!(
+            abbreviations.insert(tag, abbreviation).unwrap_err(),
+            Tb64Error::AbbreviationConflict
+        ));
+    }
+    assert!(abbreviations.insert("LONG TAG", "LT").is_err());
+    assert!(abbreviations.insert("LONG_TAG", "L~T").is_err());
+}
+
+#[test]
+fn test_deref() {
+    let tb64 = TaggedBase64::new("TAG", b"bytes").unwrap();
+    assert_eq!(tb64.len(), 5);
+    assert!(!tb64.is_empty());
+    assert_eq!(tb64.first(), Some(&b'b'));
+    assert_eq!(tb64[1], b'y');
+    assert_eq!(&tb64[1..3], b"yt");
+    assert_eq!(&*tb64, b"bytes");
+
+    // The length is that of the value, not of the displayed string.
+    assert_ne!(tb64.len(), tb64.to_string().len());
+
+    let empty = TaggedBase64::new("TAG", b"").unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_error_variants() {
+    let errors = [
+        Tb64Error::UnexpectedTag,
+        Tb64Error::InvalidTag {
+            position: 2,
+            character: ' ',
+        },
+        Tb64Error::TagContainsDelimiter,
+        Tb64Error::NonAsciiTag {
+            position: 0,
+            character: 'Σ',
+        },
+        Tb64Error::AbbreviationConflict,
+        Tb64Error::InvalidSeparator,
+        Tb64Error::InvalidDelimiter,
+        Tb64Error::MissingDelimiter,
+        Tb64Error::MissingChecksum,
+        Tb64Error::Base64 {
+            message: "oops".into(),
+        },
+        Tb64Error::InvalidByte {
+            offset: 1,
+            byte: b'/',
+        },
+        Tb64Error::InvalidLastSymbol {
+            offset: 2,
+            byte: b'F',
+        },
+        Tb64Error::InvalidChecksum,
+        Tb64Error::InvalidData,
+        Tb64Error::WrongLength {
+            expected: 32,
+            actual: 31,
+        },
+        Tb64Error::BufferTooSmall {
+            needed: 8,
+            available: 7,
+        },
+        Tb64Error::TrailingData { position: 9 },
+    ];
+    for e in errors {
+        let s = e.to_string();
+        assert!(!s.is_empty());
+        // Tb64Error is non-exhaustive, so a wildcard arm is required here.
+        let known = match e {
+            Tb64Error::Base64 { message } => s.contains(&message),
+            _ => true,
+        };
+        assert!(known, "{s}");
+    }
+}
+
+/// A `core::fmt::Write` sink with a fixed capacity, which fails rather than
+/// allocating when it is full.
+struct FixedBuf {
+    buf: [u8; 64],
+    len: usize,
+}
+
+impl core::fmt::Write for FixedBuf {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(core::fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_display_fixed_buffer() {
+    use core::fmt::Write;
+
+    let tb64 = TaggedBase64::new("TX", b"transaction identifier goes here").unwrap();
+    let mut sink = FixedBuf {
+        buf: [0; 64],
+        len: 0,
+    };
+    write!(sink, "{}", tb64).unwrap();
+    assert_eq!(
+        str::from_utf8(&sink.buf[..sink.len]).unwrap(),
+        tb64.to_string()
+    );
+
+    // A value too large for the sink fails cleanly.
+    let big = TaggedBase64::new("TX", &[7; 100]).unwrap();
+    let mut sink = FixedBuf {
+        buf: [0; 64],
+        len: 0,
+    };
+    assert!(write!(sink, "{}", big).is_err());
+
+    // Writing in chunks matches encoding the value and checksum in one go,
+    // including across chunk boundaries.
+    for len in 0..600 {
+        let value = (0..len).map(|i| (i * 31) as u8).collect::<Vec<_>>();
+        let tb64 = TaggedBase64::new("T", &value).unwrap();
+        let (_, mut bytes, checksum) = tb64.clone().into_parts();
+        bytes.push(checksum);
+        assert_eq!(
+            tb64.to_string(),
+            format!("T~{}", TaggedBase64::encode_raw(&bytes))
+        );
+    }
+}
+
+#[test]
+fn test_encoded_len() {
+    for tag in ["", "T", "LONG_tag-123"] {
+        for len in 0..100 {
+            let tb64 = TaggedBase64::new(tag, &vec![0xa5; len]).unwrap();
+            assert_eq!(tb64.encoded_len(), tb64.to_string().len());
+        }
+    }
+}
+
+#[test]
+fn test_grouped() {
+    let tb64 = TaggedBase64::new("KEY", b"public key bits").unwrap();
+    let plain = tb64.to_string();
+    assert_eq!(plain, "KEY~cHVibGljIGtleSBiaXRzCg");
+
+    let grouped = tb64.to_string_grouped(4, ' ').unwrap();
+    assert_eq!(grouped, "KEY~cHVi bGlj IGtl eSBi aXRz Cg");
+    assert_eq!(TaggedBase64::parse_grouped(&grouped, ' ').unwrap(), tb64);
+    assert!(TaggedBase64::parse(&grouped).is_err());
+
+    // A group length which divides the encoding evenly has no trailing
+    // separator.
+    let grouped = tb64.to_string_grouped(11, '.').unwrap();
+    assert_eq!(grouped, "KEY~cHVibGljIGt.leSBiaXRzCg");
+    assert_eq!(TaggedBase64::parse_grouped(&grouped, '.').unwrap(), tb64);
+
+    // Separators in unexpected places are tolerated.
+    assert_eq!(
+        TaggedBase64::parse_grouped("KEY~c.HVibGljIGtleSBiaX..RzCg.", '.').unwrap(),
+        tb64
+    );
+    // The plain form is accepted, and a group length of zero produces it.
+    assert_eq!(TaggedBase64::parse_grouped(&plain, '.').unwrap(), tb64);
+    assert_eq!(tb64.to_string_grouped(0, '.').unwrap(), plain);
+
+    for sep in ['-', '_', 'a', '0', '~'] {
+        assert!(matches!(
+            tb64.to_string_grouped(4, sep).unwrap_err(),
+            Tb64Error::InvalidSeparator
+        ));
+        assert!(matches!(
+            TaggedBase64::parse_grouped(&plain, sep).unwrap_err(),
+            Tb64Error::InvalidSeparator
+        ));
+    }
+}
+
+#[test]
+fn test_tag_bytes() {
+    let tb64 = TaggedBase64::new("KEY", b"public key bits").unwrap();
+    assert_eq!(tb64.tag_bytes(), b"KEY");
+    assert_ne!(tb64.tag_bytes(), b"TX");
+    assert_eq!(TaggedBase64::new("", b"").unwrap().tag_bytes(), b"");
+}
+
+#[test]
+fn test_replace_value() {
+    let mut tb64 = TaggedBase64::new("STATE", b"before").unwrap();
+    let old = tb64.replace_value(b"after");
+    assert_eq!(old, b"before");
+    assert_eq!(tb64.value(), b"after");
+    assert!(tb64.is_valid());
+    assert_eq!(tb64, TaggedBase64::new("STATE", b"after").unwrap());
+    assert_eq!(TaggedBase64::parse(&tb64.to_string()).unwrap(), tb64);
+}
+
+#[test]
+fn test_serde_json_owned_input() {
+    // Inputs which cannot be borrowed from must still deserialize, which
+    // requires deserializing an owned `String` before parsing.
+    let t = TaggedBase64::new("TAG", b"owned input").unwrap();
+    let v = serde_json::Value::String(t.to_string());
+    assert_eq!(t, serde_json::from_value::<TaggedBase64>(v).unwrap());
+
+    let v = serde_json::json!({ "field": t.to_string() });
+    let map: std::collections::HashMap<String, TaggedBase64> = serde_json::from_value(v).unwrap();
+    assert_eq!(t, map["field"]);
+
+    let json = serde_json::to_vec(&t).unwrap();
+    assert_eq!(
+        t,
+        serde_json::from_reader::<_, TaggedBase64>(json.as_slice()).unwrap()
+    );
+}
+
+#[test]
+fn test_looks_valid() {
+    let valid = TaggedBase64::new("TX", b"transaction").unwrap().to_string();
+    assert!(TaggedBase64::looks_valid(&valid));
+    assert!(TaggedBase64::looks_valid("A~wA"));
+    assert!(TaggedBase64::looks_valid("~AA"));
+
+    // Quick rejections.
+    for s in [
+        "", "AA", "AAA~", "A/A~wA", "A~w", "A~AAAAA", "A~w/", "A~wA~wA", "AAA~AAF",
+    ] {
+        assert!(!TaggedBase64::looks_valid(s), "{s}");
+        assert!(TaggedBase64::parse(s).is_err(), "{s}");
+    }
+
+    // A checksum failure is not detected without decoding.
+    assert!(TaggedBase64::looks_valid("AAA~AAA"));
+
+    // Exhaustively, over short strings from a small alphabet, looks_valid
+    // agrees with parse except for checksum failures.
+    let alphabet = ['A', 'Q', 'g', 'w', '_', '~', '%'];
+    let mut strings = vec![String::new()];
+    for _ in 0..5 {
+        strings = strings
+            .iter()
+            .flat_map(|s| alphabet.iter().map(move |c| format!("{s}{c}")))
+            .collect();
+        for s in &strings {
+            let structurally_valid = matches!(
+                TaggedBase64::parse(s),
+                Ok(_) | Err(Tb64Error::InvalidChecksum)
+            );
+            assert_eq!(TaggedBase64::looks_valid(s), structurally_valid, "{s}");
+        }
+    }
+}
+
+#[test]
+fn test_tag_policy() {
+    let default = TagPolicy::default();
+    let strict = TagPolicy::strict_alnum();
+    let dotted = TagPolicy::new(|c| TaggedBase64::is_safe_base64_ascii(c) || c == '.');
+
+    for (tag, default_ok, strict_ok, dotted_ok) in [
+        ("KEY", true, true, true),
+        ("", true, true, true),
+        ("MY-KEY", true, false, true),
+        ("MY_KEY", true, false, true),
+        ("my.key", false, false, true),
+        ("MY KEY", false, false, false),
+    ] {
+        for (policy, ok) in [
+            (&default, default_ok),
+            (&strict, strict_ok),
+            (&dotted, dotted_ok),
+        ] {
+            let result = TaggedBase64::new_with_policy(tag, b"bits", policy);
+            assert_eq!(result.is_ok(), ok, "{tag} {policy:?}");
+            if let Ok(tb64) = result {
+                assert_eq!(tb64.tag(), tag);
+                assert_eq!(
+                    TaggedBase64::parse_with_policy(&tb64.to_string(), policy).unwrap(),
+                    tb64
+                );
+            }
+        }
+    }
+
+    // The default policy matches new and parse.
+    let tb64 = TaggedBase64::new_with_policy("MY-KEY", b"bits", &default).unwrap();
+    assert_eq!(tb64, TaggedBase64::new("MY-KEY", b"bits").unwrap());
+
+    // A tag allowed only by a custom policy is rejected by the default parser.
+    let dotted_tb64 = TaggedBase64::new_with_policy("my.key", b"bits", &dotted).unwrap();
+    assert!(TaggedBase64::parse(&dotted_tb64.to_string()).is_err());
+    assert!(TaggedBase64::parse_with_policy("MY-KEY~Yml0c2U", &strict).is_err());
+
+    // The delimiter is never allowed, whatever the policy.
+    let anything = TagPolicy::new(|_| true);
+    assert!(matches!(
+        TaggedBase64::new_with_policy("a~b", b"", &anything).unwrap_err(),
+        Tb64Error::TagContainsDelimiter
+    ));
+}
+
+#[test]
+fn test_serde_verbose() {
+    let tb64 = TaggedBase64::new("KEY", b"public key bits").unwrap();
+    let verbose = TaggedBase64Verbose::from(tb64.clone());
+    let v = serde_json::to_value(&verbose).unwrap();
+    assert_eq!(
+        v,
+        serde_json::json!({
+            "tag": "KEY",
+            "value": TaggedBase64::encode_raw(b"public key bits"),
+            "checksum": tb64.clone().into_parts().2,
+        })
+    );
+
+    let s = serde_json::to_string(&verbose).unwrap();
+    let parsed: TaggedBase64Verbose = serde_json::from_str(&s).unwrap();
+    assert_eq!(TaggedBase64::from(parsed), tb64);
+
+    // The checksum is verified.
+    let mut bad = v.clone();
+    bad["value"] = TaggedBase64::encode_raw(b"public key bitz").into();
+    assert!(serde_json::from_value::<TaggedBase64Verbose>(bad).is_err());
+    let mut bad = v;
+    bad["tag"] = "K~Y".into();
+    assert!(serde_json::from_value::<TaggedBase64Verbose>(bad).is_err());
+}
+
+#[test]
+fn test_parse_checked() {
+    let check = |tag: &str, value: &[u8], expected: &[Tb64Warning]| {
+        let tb64 = TaggedBase64::new(tag, value).unwrap();
+        let (parsed, warnings) = TaggedBase64::parse_checked(&tb64.to_string()).unwrap();
+        assert_eq!(parsed, tb64);
+        assert_eq!(warnings, expected, "{tb64}");
+        for w in warnings {
+            assert!(!w.to_string().is_empty());
+        }
+    };
+    check("TX", b"transaction", &[]);
+    check("TX1", b"transaction", &[]);
+    check("", b"transaction", &[Tb64Warning::EmptyTag]);
+    check("12345", b"transaction", &[Tb64Warning::NumericTag]);
+    check("TX", b"", &[Tb64Warning::EmptyValue]);
+    check("", b"", &[Tb64Warning::EmptyTag, Tb64Warning::EmptyValue]);
+    check(
+        "0",
+        b"",
+        &[Tb64Warning::NumericTag, Tb64Warning::EmptyValue],
+    );
+
+    // Errors are still errors.
+    assert!(matches!(
+        TaggedBase64::parse_checked("AAA~AAA").unwrap_err(),
+        Tb64Error::InvalidChecksum
+    ));
+}
+
+/// The checksum which `new` computes for a tag and value.
+fn checksum_of(tag: &str, value: &[u8]) -> u8 {
+    TaggedBase64::new(tag, value).unwrap().into_parts().2
+}
+
+#[test]
+fn test_const_construction() {
+    const { assert!(TaggedBase64::is_safe_base64_tag("TX")) };
+    assert!(!TaggedBase64::is_safe_base64_tag("Σ"));
+
+    let tb64 = tagged_base64!("TX", b"transaction identifier goes here");
+    assert_eq!(
+        tb64,
+        TaggedBase64::new("TX", b"transaction identifier goes here").unwrap()
+    );
+    assert!(tb64.is_valid());
+
+    const GENESIS: &[u8] = &[0; 32];
+    assert_eq!(
+        tagged_base64!("", GENESIS),
+        TaggedBase64::new("", &[0; 32]).unwrap()
+    );
+}
+
+#[test]
+fn test_validate_tag() {
+    for tag in ["", "KEY", "abc-XYZ_0123456789"] {
+        TaggedBase64::validate_tag(tag).unwrap();
+    }
+
+    for (tag, position, character) in [
+        ("Oh, no!", 2, ','),
+        (" KEY", 0, ' '),
+        ("KEY.", 3, '.'),
+        ("K/Y~", 1, '/'),
+    ] {
+        match TaggedBase64::validate_tag(tag).unwrap_err() {
+            Tb64Error::InvalidTag {
+                position: p,
+                character: c,
+            } => assert_eq!((p, c), (position, character), "{tag}"),
+            e => panic!("unexpected error for {tag}: {e}"),
+        }
+    }
+    assert_eq!(
+        TaggedBase64::validate_tag("Oh, no!")
+            .unwrap_err()
+            .to_string(),
+        "invalid character ',' at position 2 in the tag"
+    );
+
+    assert!(matches!(
+        TaggedBase64::validate_tag("KEY~").unwrap_err(),
+        Tb64Error::TagContainsDelimiter
+    ));
+
+    // Every entry point reports the first bad character in the same way.
+    for tag in ["K/Y~", "K~Y/", "Oh, no!", "KEYΣ"] {
+        let expected = TaggedBase64::validate_tag(tag).unwrap_err().to_string();
+        assert_eq!(
+            TagPolicy::default().check(tag).unwrap_err().to_string(),
+            expected
+        );
+        assert_eq!(
+            TaggedBase64::new(tag, b"").unwrap_err().to_string(),
+            expected
+        );
+        if !tag.contains('~') {
+            assert_eq!(
+                TaggedBase64::parse(&format!("{tag}~AAAA"))
+                    .unwrap_err()
+                    .to_string(),
+                expected
+            );
+        }
+    }
+    assert!(matches!(
+        TaggedBase64::new("K~Y/", b""),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+
+    for (tag, position) in [("Σ", 0), ("KEYΣ", 3), ("Ünïcode", 0), ("tag·", 3)] {
+        for err in [
+            TaggedBase64::validate_tag(tag).unwrap_err(),
+            TaggedBase64::new(tag, b"").unwrap_err(),
+        ] {
+            match err {
+                Tb64Error::NonAsciiTag {
+                    position: p,
+                    character: c,
+                } => {
+                    assert_eq!(p, position, "{tag}");
+                    assert_eq!(Some(c), tag[p..].chars().next(), "{tag}");
+                }
+                e => panic!("unexpected error for {tag}: {e}"),
+            }
+        }
+    }
+    assert_eq!(
+        TaggedBase64::validate_tag("KEYΣ").unwrap_err().to_string(),
+        "non-ASCII character 'Σ' at position 3 in the tag; tags must be ASCII"
+    );
+}
+
+#[cfg(feature = "tokio-codec")]
+#[test]
+fn test_codec() {
+    use bytes::BytesMut;
+    use tagged_base64::codec::{CodecError, TaggedBase64Codec};
+    use tokio_util::codec::{Decoder, Encoder};
+
+    let values = [
+        TaggedBase64::new("TX", b"transaction identifier goes here").unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+        TaggedBase64::new("KEY", b"public key bits").unwrap(),
+    ];
+    let mut codec = TaggedBase64Codec::new();
+    let mut encoded = BytesMut::new();
+    for v in &values {
+        codec.encode(v, &mut encoded).unwrap();
+    }
+    codec.encode(values[0].clone(), &mut encoded).unwrap();
+    assert_eq!(
+        encoded,
+        format!(
+            "{}\n{}\n{}\n{}\n",
+            values[0], values[1], values[2], values[0]
+        )
+        .as_bytes()
+    );
+
+    // Feed the stream a few bytes at a time, so lines are split across reads.
+    for chunk_size in [1, 2, 5, 17, encoded.len()] {
+        let mut codec = TaggedBase64Codec::new();
+        let mut buf = BytesMut::new();
+        let mut decoded = vec![];
+        for chunk in encoded.chunks(chunk_size) {
+            buf.extend_from_slice(chunk);
+            while let Some(v) = codec.decode(&mut buf).unwrap() {
+                decoded.push(v);
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(decoded, [&values[..], &values[..1]].concat());
+    }
+
+    // CRLF line endings and a final line without a line ending.
+    let mut codec = TaggedBase64Codec::new();
+    let mut buf = BytesMut::from(format!("{}\r\n{}", values[2], values[0]).as_bytes());
+    assert_eq!(codec.decode(&mut buf).unwrap(), Some(values[2].clone()));
+    assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    assert_eq!(codec.decode_eof(&mut buf).unwrap(), Some(values[0].clone()));
+    assert_eq!(codec.decode_eof(&mut buf).unwrap(), None);
+
+    // Errors in a line are reported.
+    let mut buf = BytesMut::from(&b"AAA~AAA\n"[..]);
+    assert!(matches!(
+        TaggedBase64Codec::new().decode(&mut buf).unwrap_err(),
+        CodecError::Parse {
+            source: Tb64Error::InvalidChecksum
+        }
+    ));
+    let mut buf = BytesMut::from(&b"A~\xff\n"[..]);
+    assert!(matches!(
+        TaggedBase64Codec::new().decode(&mut buf).unwrap_err(),
+        CodecError::InvalidUtf8
+    ));
+
+    // Lines longer than the maximum are rejected without buffering them,
+    // and decoding resumes at the next line.
+    let max_length = values[2].to_string().len();
+    let long = TaggedBase64::new("KEY", &[7; 64]).unwrap().to_string();
+    let stream = format!("{}\n{}\n{}\r\n{}", long, values[2], values[2], long);
+    for chunk_size in [1, 3, stream.len()] {
+        let mut codec = TaggedBase64Codec::new_with_max_length(max_length);
+        assert_eq!(codec.max_length(), max_length);
+        let mut buf = BytesMut::new();
+        let mut decoded = vec![];
+        for chunk in stream.as_bytes().chunks(chunk_size) {
+            buf.extend_from_slice(chunk);
+            loop {
+                match codec.decode(&mut buf) {
+                    Ok(Some(v)) => decoded.push(Ok(v)),
+                    Ok(None) => break,
+                    Err(e) => decoded.push(Err(e)),
+                }
+            }
+            // At most one partial line is held back.
+            assert!(buf.len() <= max_length + 1);
+        }
+        match codec.decode_eof(&mut buf) {
+            Ok(Some(v)) => decoded.push(Ok(v)),
+            Ok(None) => {}
+            Err(e) => decoded.push(Err(e)),
+        }
+        assert!(matches!(
+            &decoded[..],
+            [
+                Err(CodecError::LineTooLong { max_length: m1 }),
+                Ok(v1),
+                Ok(v2),
+                Err(CodecError::LineTooLong { max_length: m2 }),
+            ] if *m1 == max_length && *m2 == max_length && *v1 == values[2] && *v2 == values[2]
+        ));
+    }
+}
+
+#[test]
+fn test_tag_with_ellipsis() {
+    let tb64 = TaggedBase64::new("TX", b"transaction identifier goes here").unwrap();
+    assert_eq!(tb64.tag_with_ellipsis(), "TX~…");
+    assert_eq!(
+        TaggedBase64::new("", b"").unwrap().tag_with_ellipsis(),
+        "~…"
+    );
+}
+
+#[test]
+fn test_full_len_checksum() {
+    for len in [0, 1, 255, 256, 257, 1000] {
+        let tb64 = TaggedBase64::new("BIG", &vec![0x5a; len]).unwrap();
+        let s = tb64.to_string_full_len();
+        assert_eq!(TaggedBase64::parse_full_len(&s).unwrap(), tb64);
+    }
+
+    // With an empty tag and all-zero values, the CRC is zero, so the default
+    // checksum depends only on the length modulo 256 and collides for these
+    // lengths.
+    let short = vec![0; 1];
+    let long = vec![0; 257];
+    assert_eq!(checksum_of("", &short), checksum_of("", &long));
+
+    // The full-length checksum tells them apart.
+    let last_byte = |value: &[u8]| {
+        let s = TaggedBase64::new("", value).unwrap().to_string_full_len();
+        *TaggedBase64::decode_raw(&s[1..]).unwrap().last().unwrap()
+    };
+    assert_ne!(last_byte(&short), last_byte(&long));
+
+    // The formats are not interchangeable.
+    let tb64 = TaggedBase64::new("BIG", &long).unwrap();
+    assert!(TaggedBase64::parse(&tb64.to_string_full_len()).is_err());
+    assert!(TaggedBase64::parse_full_len(&tb64.to_string()).is_err());
+}
+
+fn check_value_array<const N: usize>(tb64: &TaggedBase64) {
+    if tb64.value().len() == N {
+        let array: [u8; N] = tb64.value_array().unwrap();
+        assert_eq!(array.as_slice(), tb64.value().as_slice());
+    } else {
+        match tb64.value_array::<N>().unwrap_err() {
+            Tb64Error::WrongLength { expected, actual } => {
+                assert_eq!(expected, N);
+                assert_eq!(actual, tb64.value().len());
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+}
+
+#[test]
+fn test_value_array() {
+    let key = TaggedBase64::new("KEY", &[7; 32]).unwrap();
+    assert_eq!(key.value_array::<32>().unwrap(), [7; 32]);
+    check_value_array::<0>(&key);
+    check_value_array::<31>(&key);
+    check_value_array::<32>(&key);
+    check_value_array::<33>(&key);
+
+    let empty = TaggedBase64::new("KEY", &[]).unwrap();
+    check_value_array::<0>(&empty);
+    check_value_array::<32>(&empty);
+
+    assert_eq!(
+        key.value_array::<16>().unwrap_err().to_string(),
+        "expected a value of 16 bytes, found 32 bytes"
+    );
+}
+
+#[test]
+fn test_parse_value_only() {
+    for (tag, value) in [("TX", &b"transaction"[..]), ("", b"no tag"), ("KEY", b"")] {
+        let tb64 = TaggedBase64::new(tag, value).unwrap();
+        let s = tb64.to_string();
+        let (_, value_base64) = s.split_once('~').unwrap();
+        let parsed = TaggedBase64::parse_value_only(tag, value_base64).unwrap();
+        assert_eq!(parsed, tb64);
+        assert_eq!(
+            TaggedBase64::parse(&format!("{tag}~{value_base64}")).unwrap(),
+            parsed
+        );
+    }
+
+    // The checksum covers the tag, so the wrong implied tag is detected.
+    let s = TaggedBase64::new("TX", b"transaction").unwrap().to_string();
+    let (_, value_base64) = s.split_once('~').unwrap();
+    assert!(matches!(
+        TaggedBase64::parse_value_only("BLOCK", value_base64),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_value_only("TX", ""),
+        Err(Tb64Error::MissingChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_value_only("TX", &s),
+        Err(Tb64Error::InvalidByte { .. })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_value_only("T X", value_base64),
+        Err(Tb64Error::InvalidTag {
+            position: 1,
+            character: ' '
+        })
+    ));
+}
+
+#[cfg(feature = "c-ffi")]
+#[test]
+fn test_ffi_ptr_len() {
+    for (tag, value) in [("TX", &b"transaction"[..]), ("", b""), ("KEY", &[0xff; 32])] {
+        let tb64 = TaggedBase64::new(tag, value).unwrap();
+
+        let (ptr, len) = tb64.tag_ptr_len();
+        // Safety: tb64 is alive and unmodified.
+        let tag_bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+        assert_eq!(tag_bytes, tb64.tag().as_bytes());
+
+        let (ptr, len) = tb64.value_ptr_len();
+        // Safety: tb64 is alive and unmodified.
+        let value_bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+        assert_eq!(value_bytes, tb64.value().as_slice());
+
+        // The pointers borrow rather than copy.
+        assert_eq!(tb64.tag_ptr_len().0, tb64.tag_bytes().as_ptr());
+        assert_eq!(tb64.value_ptr_len().0, tb64.as_ptr());
+    }
+}
+
+#[test]
+fn test_value_eq() {
+    let old = TaggedBase64::new("OLD", b"payload").unwrap();
+    let new = TaggedBase64::new("NEW", b"payload").unwrap();
+    assert!(old.value_eq(&new));
+    assert!(new.value_eq(&old));
+    assert_ne!(old, new);
+
+    let other = TaggedBase64::new("OLD", b"other payload").unwrap();
+    assert!(!old.value_eq(&other));
+    assert!(old.value_eq(&old));
+}
+
+#[tagged("FIELD", canonical_bytes)]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct Field(ark_bls12_381::Fr);
+
+#[tagged("FIELD", compressed, canonical_bytes)]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct FieldCompressed(ark_bls12_381::Fr);
+
+#[test]
+fn test_canonical_bytes() {
+    use ark_std::UniformRand;
+    use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+
+    let mut rng = ChaChaRng::from_seed([42; 32]);
+    let fr = ark_bls12_381::Fr::rand(&mut rng);
+
+    let field = Field(fr);
+    let mut expected = vec![];
+    fr.serialize_uncompressed(&mut expected).unwrap();
+    assert_eq!(field.canonical_bytes(), expected);
+    assert_eq!(TaggedBase64::from(&field).value(), field.canonical_bytes());
+
+    let field = FieldCompressed(fr);
+    let mut expected = vec![];
+    fr.serialize_compressed(&mut expected).unwrap();
+    assert_eq!(field.canonical_bytes(), expected);
+    assert_eq!(TaggedBase64::from(&field).value(), field.canonical_bytes());
+}
+
+// With `no_serde`, hand-written serde impls do not conflict with the macro.
+#[tagged("CUSTOM", no_serde)]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct CustomSerde(u32);
+
+impl serde::Serialize for CustomSerde {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.0)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CustomSerde {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <u32 as serde::Deserialize>::deserialize(deserializer).map(Self)
+    }
+}
+
+#[test]
+fn test_no_serde() {
+    let custom = CustomSerde(42);
+    assert_eq!(serde_json::to_string(&custom).unwrap(), "42");
+    assert_eq!(serde_json::from_str::<CustomSerde>("42").unwrap(), custom);
+
+    // The other impls are still generated.
+    let s = custom.to_string();
+    assert!(s.starts_with("CUSTOM~"));
+    assert_eq!(s.parse::<CustomSerde>().unwrap(), custom);
+    assert_eq!(
+        CustomSerde::try_from(TaggedBase64::parse(&s).unwrap()).unwrap(),
+        custom
+    );
+}
+
+#[tagged("KEY", len = 32)]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct Key([u8; 32]);
+
+#[test]
+fn test_expected_len() {
+    assert_eq!(<Key as Tagged>::expected_len(), Some(32));
+    assert_eq!(<Blob as Tagged>::expected_len(), None);
+
+    let key = Key([3; 32]);
+    assert_eq!(TaggedBase64::from(&key).value().len(), 32);
+    assert_eq!(key.to_string().parse::<Key>().unwrap(), key);
+
+    let empty = TaggedBase64::new("KEY", b"").unwrap();
+    assert!(matches!(
+        Key::try_from(&empty),
+        Err(Tb64Error::WrongLength {
+            expected: 32,
+            actual: 0
+        })
+    ));
+    assert!(serde_json::from_str::<Key>(&format!("\"{}\"", empty)).is_err());
+
+    // Without a length hint, an empty value is passed to the deserializer.
+    let empty = TaggedBase64::new("BLOB", b"").unwrap();
+    assert!(matches!(
+        Blob::try_from(&empty),
+        Err(Tb64Error::InvalidData)
+    ));
+}
+
+#[test]
+fn test_checksum_matches() {
+    let s = TaggedBase64::new("TX", b"transaction").unwrap().to_string();
+    assert!(TaggedBase64::checksum_matches(&s).unwrap());
+
+    // Well-formed, but the checksum does not cover this tag.
+    let (_, value) = s.split_once('~').unwrap();
+    assert!(!TaggedBase64::checksum_matches(&format!("BLOCK~{value}")).unwrap());
+
+    // Not well-formed.
+    assert!(matches!(
+        TaggedBase64::checksum_matches("TX"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::checksum_matches("TX~"),
+        Err(Tb64Error::MissingChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::checksum_matches("T X~AAAA"),
+        Err(Tb64Error::InvalidTag {
+            position: 1,
+            character: ' '
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::checksum_matches("TX~A/AA"),
+        Err(Tb64Error::InvalidByte { .. })
+    ));
+}
+
+#[test]
+fn test_js_wrapper_native() {
+    let tb64 = TaggedBase64::new("Tag47", b"Just some bits").unwrap();
+    let mut jstb64 = JsTaggedBase64::from(tb64.clone());
+    assert_eq!(jstb64, tb64);
+    assert_eq!(jstb64.tag(), "Tag47");
+    assert_eq!(jstb64.value(), b"Just some bits");
+    assert_eq!(JsTaggedBase64::to_string(&jstb64), tb64.to_string());
+    assert_eq!(format!("{jstb64}"), tb64.to_string());
+
+    jstb64.set_tag("Godzilla");
+    jstb64.set_value(b"forest");
+    let expected = TaggedBase64::new("Godzilla", b"forest").unwrap();
+    assert_eq!(jstb64, expected);
+    assert_eq!(JsTaggedBase64::to_string(&jstb64), expected.to_string());
+}
+
+#[test]
+fn test_parse_any_tag() {
+    for tag in ["", "TX", "new-tag_2"] {
+        let tb64 = TaggedBase64::new(tag, b"payload").unwrap();
+        let s = tb64.to_string();
+        assert_eq!(TaggedBase64::parse_any_tag(&s).unwrap(), tb64);
+        assert_eq!(TaggedBase64::parse_expecting(&s, tag).unwrap(), tb64);
+    }
+
+    let s = TaggedBase64::new("TX", b"payload").unwrap().to_string();
+    assert!(matches!(
+        TaggedBase64::parse_expecting(&s, "BLOCK"),
+        Err(Tb64Error::UnexpectedTag)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_expecting(&s, ""),
+        Err(Tb64Error::UnexpectedTag)
+    ));
+
+    // The value is still verified strictly.
+    let (_, value) = s.split_once('~').unwrap();
+    let wrong_tag = format!("BLOCK~{value}");
+    assert!(matches!(
+        TaggedBase64::parse_any_tag(&wrong_tag),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_any_tag("TX~"),
+        Err(Tb64Error::MissingChecksum)
+    ));
+}
+
+#[test]
+fn test_context_checksum() {
+    for context in ["", "payments-v1", "a~b"] {
+        for (tag, value) in [("TX", &b"transaction"[..]), ("", b""), ("KEY", &[0; 300])] {
+            let tb64 = TaggedBase64::new(tag, value).unwrap();
+            let s = tb64.to_string_with_context(context);
+            let parsed = TaggedBase64::parse_with_context(&s, context).unwrap();
+            // Only the string form depends on the context; the parsed value
+            // carries the plain checksum.
+            assert!(parsed.is_valid());
+            assert_eq!(parsed, tb64);
+            assert_eq!(parsed.to_string(), tb64.to_string());
+        }
+    }
+
+    // A string from one context is rejected in another, and by plain parse.
+    let s = TaggedBase64::new("TX", b"transaction")
+        .unwrap()
+        .to_string_with_context("payments-v1");
+    for context in ["payments-v2", "", "payments-v1 "] {
+        assert!(matches!(
+            TaggedBase64::parse_with_context(&s, context),
+            Err(Tb64Error::InvalidChecksum)
+        ));
+    }
+    assert!(matches!(
+        TaggedBase64::parse(&s),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    let plain = TaggedBase64::new("TX", b"transaction").unwrap().to_string();
+    assert!(TaggedBase64::parse_with_context(&plain, "payments-v1").is_err());
+
+    // Moving bytes between the context and the tag changes the checksum.
+    let a = TaggedBase64::new("BC", b"value")
+        .unwrap()
+        .to_string_with_context("A");
+    let b = TaggedBase64::new("C", b"value")
+        .unwrap()
+        .to_string_with_context("AB");
+    assert_ne!(a["BC~".len()..], b["C~".len()..]);
+
+    assert!(matches!(
+        TaggedBase64::parse_with_context("TX~", "ctx"),
+        Err(Tb64Error::MissingChecksum)
+    ));
+}
+
+#[test]
+fn test_encode_raw_into() {
+    for len in 0..10 {
+        let input: Vec<u8> = (0..len).collect();
+        let expected = TaggedBase64::encode_raw(&input);
+
+        // Exact buffer.
+        let mut exact = vec![0; expected.len()];
+        assert_eq!(
+            TaggedBase64::encode_raw_into(&input, &mut exact).unwrap(),
+            expected.len()
+        );
+        assert_eq!(exact, expected.as_bytes());
+
+        // Oversized buffer: the rest is untouched.
+        let mut big = [b'.'; 32];
+        let n = TaggedBase64::encode_raw_into(&input, &mut big).unwrap();
+        assert_eq!(&big[..n], expected.as_bytes());
+        assert!(big[n..].iter().all(|b| *b == b'.'));
+
+        // Small buffer.
+        if !expected.is_empty() {
+            let mut small = vec![b'.'; expected.len() - 1];
+            match TaggedBase64::encode_raw_into(&input, &mut small).unwrap_err() {
+                Tb64Error::BufferTooSmall { needed, available } => {
+                    assert_eq!(needed, expected.len());
+                    assert_eq!(available, small.len());
+                }
+                e => panic!("unexpected error: {e}"),
+            }
+            assert!(small.iter().all(|b| *b == b'.'));
+        }
+    }
+}
+
+#[derive(Tagged)]
+#[tagged_tag = "LIGHT"]
+#[tagged_const_tag]
+struct Light;
+
+#[derive(Tagged)]
+#[tagged_tag = "GENERIC"]
+#[tagged_const_tag]
+struct GenericLight<T: Clone>(#[allow(dead_code)] T);
+
+#[test]
+fn test_derive_tagged() {
+    assert_eq!(Light::tag(), "LIGHT");
+    assert_eq!(<Light as Tagged>::expected_len(), None);
+    assert_eq!(GenericLight::<u8>::tag(), "GENERIC");
+    let _ = Light;
+}
+
+#[test]
+fn test_tag_interner() {
+    let mut interner = TagInterner::new();
+    assert!(interner.is_empty());
+
+    let a = TaggedBase64::new_interned(&mut interner, "TX", b"first").unwrap();
+    let b = TaggedBase64::new_interned(&mut interner, "TX", b"second").unwrap();
+    let c = TaggedBase64::new_interned(&mut interner, "BLOCK", b"third").unwrap();
+    assert_eq!(interner.len(), 2);
+
+    // Values with the same tag share storage, where atomics make that
+    // possible.
+    #[cfg(target_has_atomic = "ptr")]
+    {
+        assert_eq!(a.tag_bytes().as_ptr(), b.tag_bytes().as_ptr());
+        assert_ne!(a.tag_bytes().as_ptr(), c.tag_bytes().as_ptr());
+        // So do clones.
+        assert_eq!(a.clone().tag_bytes().as_ptr(), a.tag_bytes().as_ptr());
+    }
+
+    // Interned values are indistinguishable from other values.
+    assert_eq!(a, TaggedBase64::new("TX", b"first").unwrap());
+    assert_eq!(
+        TaggedBase64::parse(&b.to_string()).unwrap(),
+        TaggedBase64::new("TX", b"second").unwrap()
+    );
+    assert_eq!(
+        format!("{a:?}"),
+        format!("{:?}", TaggedBase64::new("TX", b"first").unwrap())
+    );
+
+    assert!(matches!(
+        TaggedBase64::new_interned(&mut interner, "T~X", b""),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+    assert_eq!(interner.len(), 2);
+}
+
+#[cfg(feature = "json-schema")]
+#[test]
+fn test_json_schema() {
+    use tagged_base64::schema::{json_schema, json_schema_for};
+
+    let schema = json_schema_for::<Key>().unwrap();
+    assert_eq!(schema["type"], "string");
+    assert_eq!(schema["pattern"], "^KEY~[A-Za-z0-9_-]+$");
+    let key = Key([0; 32]).to_string();
+    assert_eq!(schema["minLength"], key.len());
+    assert_eq!(schema["maxLength"], key.len());
+
+    let schema = json_schema_for::<Blob>().unwrap();
+    assert_eq!(schema["pattern"], "^BLOB~[A-Za-z0-9_-]+$");
+    assert!(schema.get("minLength").is_none());
+
+    assert_eq!(
+        json_schema("", None).unwrap()["pattern"],
+        "^~[A-Za-z0-9_-]+$"
+    );
+    assert_eq!(
+        json_schema("TX", Some(0)).unwrap()["maxLength"],
+        TaggedBase64::new("TX", b"").unwrap().to_string().len()
+    );
+
+    // Tags are pasted into the pattern, so they must be valid.
+    assert!(matches!(
+        json_schema("T.*", None),
+        Err(Tb64Error::InvalidTag {
+            position: 1,
+            character: '.'
+        })
+    ));
+    assert!(matches!(
+        json_schema("T~X", None),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+}
+
+#[test]
+fn test_from_legacy() {
+    // Legacy strings are the tag, the delimiter and the base64 value, with
+    // no checksum byte.
+    for (legacy, tag, value) in [
+        ("TX~dHJhbnNhY3Rpb24", "TX", &b"transaction"[..]),
+        ("~AAEC", "", &[0, 1, 2][..]),
+        ("KEY~", "KEY", &b""[..]),
+    ] {
+        let upgraded = TaggedBase64::from_legacy(legacy).unwrap();
+        assert_eq!(upgraded, TaggedBase64::new(tag, value).unwrap());
+        assert!(upgraded.is_valid());
+        assert_eq!(
+            TaggedBase64::parse(&upgraded.to_string()).unwrap(),
+            upgraded
+        );
+    }
+
+    assert!(matches!(
+        TaggedBase64::from_legacy("dHJhbnNhY3Rpb24"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_legacy("T X~AAEC"),
+        Err(Tb64Error::InvalidTag {
+            position: 1,
+            character: ' '
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::from_legacy("TX~A/EC"),
+        Err(Tb64Error::InvalidByte { .. })
+    ));
+}
+
+#[test]
+fn test_inspect() {
+    for len in 0..40 {
+        let value: Vec<u8> = (0..len).collect();
+        let s = TaggedBase64::new("TX", &value).unwrap().to_string();
+        let inspection = TaggedBase64::inspect(&s).unwrap();
+        assert_eq!(inspection.tag, "TX");
+        assert_eq!(
+            inspection.value_len,
+            TaggedBase64::parse(&s).unwrap().value().len()
+        );
+        assert_eq!(TaggedBase64::split_tag(&s).unwrap(), "TX");
+    }
+
+    let inspection = TaggedBase64::inspect("~AA").unwrap();
+    assert_eq!(inspection.tag, "");
+    assert_eq!(inspection.value_len, 0);
+
+    // The value is not validated.
+    assert_eq!(TaggedBase64::split_tag("TX~not base64").unwrap(), "TX");
+
+    for (s, err) in [
+        ("TX~", Tb64Error::MissingChecksum),
+        ("TX~A", Tb64Error::MissingChecksum),
+        ("TX", Tb64Error::MissingDelimiter),
+        (
+            "T X~AA",
+            Tb64Error::InvalidTag {
+                position: 1,
+                character: ' ',
+            },
+        ),
+    ] {
+        assert_eq!(
+            TaggedBase64::inspect(s).unwrap_err().to_string(),
+            err.to_string()
+        );
+    }
+}
+
+#[test]
+fn test_to_string_lower_tag() {
+    let tb64 = TaggedBase64::new("Tx-ID_9", b"transaction").unwrap();
+    let s = tb64.to_string_lower_tag();
+    assert!(s.starts_with("tx-id_9~"));
+
+    let parsed = TaggedBase64::parse(&s).unwrap();
+    assert_eq!(parsed.tag(), "tx-id_9");
+    assert_eq!(parsed.value(), tb64.value());
+    // The lowercased form is a different value.
+    assert_ne!(parsed, tb64);
+
+    // An already lowercase tag is unchanged.
+    let lower = TaggedBase64::new("tx", b"transaction").unwrap();
+    assert_eq!(lower.to_string_lower_tag(), lower.to_string());
+}
+
+#[test]
+fn test_checksum_covers_tag() {
+    let value = b"the same value";
+    let long_tag: String = (0..1000)
+        .map(|i| ['A', 'b', '7', '-', '_'][i % 5])
+        .collect();
+
+    // Changing any single character of the tag changes the checksum. CRC-8
+    // detects every error confined to 8 consecutive bits, so this holds
+    // however long the tag is.
+    let checksum = checksum_of(&long_tag, value);
+    let s = TaggedBase64::new(&long_tag, value).unwrap().to_string();
+    let (_, encoded) = s.split_once('~').unwrap();
+    for i in (0..long_tag.len()).step_by(7) {
+        let mut tag = long_tag.clone().into_bytes();
+        tag[i] = if tag[i] == b'Z' { b'Y' } else { b'Z' };
+        let tag = String::from_utf8(tag).unwrap();
+        assert_ne!(checksum_of(&tag, value), checksum);
+
+        // So moving the value to a different tag is detected.
+        assert!(matches!(
+            TaggedBase64::parse(&format!("{tag}~{encoded}")),
+            Err(Tb64Error::InvalidChecksum)
+        ));
+    }
+
+    // Tags of different lengths give different checksums.
+    let checksums: Vec<u8> = (1..=8)
+        .map(|len| checksum_of(&long_tag[..len], value))
+        .collect();
+    for (i, a) in checksums.iter().enumerate() {
+        for b in &checksums[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+
+    // Different tags with the same value give different strings.
+    let a = TaggedBase64::new("TX", value).unwrap().to_string();
+    let b = TaggedBase64::new("TY", value).unwrap().to_string();
+    assert_ne!(a, b);
+    assert_ne!(a["TX~".len()..], b["TY~".len()..]);
+}
+
+#[test]
+fn test_eq_str() {
+    let tb64 = TaggedBase64::new("KEY", b"value").unwrap();
+    let s = tb64.to_string();
+    assert_eq!(s.as_str(), tb64);
+    assert_eq!(*s.as_str(), tb64);
+    assert!("KEY~dmFsdWVj" == tb64);
+
+    assert_ne!("KEY~dmFsdWVk", tb64);
+    assert_ne!("KEY~dmFsdWV", tb64);
+    assert_ne!("KEY~dmFsdWVjA", tb64);
+    assert_ne!("KEY", tb64);
+    assert_ne!("", tb64);
+    assert_ne!("KEZ~dmFsdWVj", tb64);
+    // The comparison is with the canonical form, so other spellings differ.
+    assert_ne!("KEY%7EdmFsdWVj", tb64);
+
+    // A long value is compared a chunk at a time.
+    let long = TaggedBase64::new("KEY", &[0x5a; 1000]).unwrap();
+    let mut s = long.to_string();
+    assert_eq!(s.as_str(), long);
+    s.replace_range(s.len() - 2.., "AA");
+    assert_ne!(s.as_str(), long);
+}
+
+/// Runs the command line tool with `args` and the extra environment
+/// variables `env`, feeding it `input`, and returns whether it succeeded and
+/// what it wrote to stdout.
+#[cfg(all(feature = "build-cli", not(target_arch = "wasm32")))]
+fn run_cli(args: &[&str], env: &[(&str, &str)], input: &[u8]) -> (bool, Vec<u8>) {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tagged-base64"))
+        .args(args)
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (output.status.success(), output.stdout)
+}
+
+#[cfg(all(feature = "build-cli", not(target_arch = "wasm32")))]
+#[test]
+fn test_cli_checksum() {
+    let cli = |args: &[&str], input: &[u8]| run_cli(args, &[], input);
+
+    let value = b"some raw data";
+    for checksum in ["crc8", "crc32", "none"] {
+        let (ok, encoded) = cli(&["--tag", "RAW", "--checksum", checksum], value);
+        assert!(ok, "{checksum}");
+        let encoded = String::from_utf8(encoded).unwrap();
+        let encoded = encoded.trim_end();
+        let (ok, decoded) = cli(&["-d", encoded, "--checksum", checksum], b"");
+        assert!(ok, "{checksum}");
+        assert_eq!(decoded, value, "{checksum}");
+    }
+
+    let tb64 = TaggedBase64::new("RAW", value).unwrap();
+    let (_, crc32) = cli(&["--tag", "RAW", "--checksum", "crc32"], value);
+    assert_eq!(
+        String::from_utf8(crc32).unwrap(),
+        tb64.to_string_crc32() + "\n"
+    );
+    // The default is the standard checksum.
+    let (_, crc8) = cli(&["--tag", "RAW"], value);
+    assert_eq!(String::from_utf8(crc8).unwrap(), tb64.to_string() + "\n");
+
+    // Decoding expects the selected checksum.
+    let (ok, _) = cli(&["-d", &tb64.to_string_crc32()], b"");
+    assert!(!ok);
+    let (ok, _) = cli(&["-d", &tb64.to_string(), "--checksum", "crc32"], b"");
+    assert!(!ok);
+}
+
+#[test]
+fn test_parse_partial() {
+    let value: Vec<u8> = (0..30).collect();
+    let s = TaggedBase64::new("LOG", &value).unwrap().to_string();
+    let encoded_len = s.len() - "LOG~".len();
+
+    for cut in 0..=encoded_len {
+        let truncated = &s[.."LOG~".len() + cut];
+        let (tag, bytes) = TaggedBase64::parse_partial(truncated).unwrap();
+        assert_eq!(tag, "LOG");
+        // Only whole groups of 4 symbols are decoded, 3 bytes each.
+        assert_eq!(bytes.len(), cut / 4 * 3, "cut at {cut}");
+        let n = bytes.len().min(value.len());
+        assert_eq!(bytes[..n], value[..n], "cut at {cut}");
+        if cut < encoded_len {
+            assert!(TaggedBase64::parse(truncated).is_err());
+        }
+    }
+
+    // Without truncation, the checksum is included.
+    let (_, bytes) = TaggedBase64::parse_partial("LOG~AAEC").unwrap();
+    assert_eq!(bytes, [0, 1, 2]);
+
+    assert!(matches!(
+        TaggedBase64::parse_partial("LOG"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_partial("L G~AAEC"),
+        Err(Tb64Error::InvalidTag {
+            position: 1,
+            character: ' '
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_partial("LOG~A/EC"),
+        Err(Tb64Error::InvalidByte { .. })
+    ));
+    // Groups are counted in bytes, so a cut may fall inside a character.
+    assert!(matches!(
+        TaggedBase64::parse_partial("LOG~AAAΣx"),
+        Err(Tb64Error::InvalidByte { offset: 3, .. })
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_writer() {
+    use std::io::Write;
+    use tagged_base64::writer::TaggedBase64Writer;
+
+    let value: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    for len in [0, 1, 2, 3, 4, 5, 6, 7, 255, 256, 1000] {
+        let expected = TaggedBase64::new("STREAM", &value[..len])
+            .unwrap()
+            .to_string();
+        // Write in chunks of every size, so that groups are split in every
+        // possible way.
+        for chunk in 1..=7 {
+            let mut writer = TaggedBase64Writer::new("STREAM").unwrap();
+            for bytes in value[..len].chunks(chunk) {
+                writer.write_all(bytes).unwrap();
+            }
+            writer.flush().unwrap();
+            assert_eq!(writer.finish(), expected, "len {len}, chunks of {chunk}");
+        }
+    }
+
+    // Serializers can write directly into the adapter.
+    let mut writer = TaggedBase64Writer::new("JSON").unwrap();
+    serde_json::to_writer(&mut writer, &vec![1, 2, 3]).unwrap();
+    assert_eq!(
+        writer.finish(),
+        TaggedBase64::new("JSON", b"[1,2,3]").unwrap().to_string()
+    );
+
+    assert!(matches!(
+        TaggedBase64Writer::new("T~X"),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+}
+
+#[test]
+fn test_delim() {
+    let tb64 = TaggedBase64::new("TX", b"transaction").unwrap();
+    for delim in ['.', '~', ':', '·'] {
+        let s = tb64.to_string_with_delim(delim).unwrap();
+        assert_eq!(s, tb64.to_string().replace('~', &delim.to_string()));
+        assert_eq!(TaggedBase64::parse_with_delim(&s, delim).unwrap(), tb64);
+    }
+    assert_eq!(tb64.to_string_with_delim('~').unwrap(), tb64.to_string());
+
+    for delim in ['a', 'Z', '0', '-', '_'] {
+        assert!(matches!(
+            tb64.to_string_with_delim(delim),
+            Err(Tb64Error::InvalidDelimiter)
+        ));
+        assert!(matches!(
+            TaggedBase64::parse_with_delim("TX.AAAA", delim),
+            Err(Tb64Error::InvalidDelimiter)
+        ));
+    }
+    assert!(matches!(
+        TaggedBase64::parse_with_delim(&tb64.to_string(), '.'),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_with_delim("TX.", '.'),
+        Err(Tb64Error::MissingChecksum)
+    ));
+}
+
+#[tagged("DOTTED", delim = ".")]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct Dotted(u64);
+
+#[tagged("COLON", delim = ':')]
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+struct Colon(u64);
+
+#[test]
+fn test_macro_delim() {
+    let dotted = Dotted(42);
+    let s = dotted.to_string();
+    assert_eq!(
+        s,
+        TaggedBase64::from(&dotted)
+            .to_string_with_delim('.')
+            .unwrap()
+    );
+    assert!(s.starts_with("DOTTED."));
+    assert_eq!(s.parse::<Dotted>().unwrap(), dotted);
+    assert!(TaggedBase64::from(&dotted)
+        .to_string()
+        .parse::<Dotted>()
+        .is_err());
+
+    let colon = Colon(7);
+    assert!(colon.to_string().starts_with("COLON:"));
+    assert_eq!(colon.to_string().parse::<Colon>().unwrap(), colon);
+
+    // Serialization still uses the usual delimiter.
+    let json = serde_json::to_string(&dotted).unwrap();
+    assert!(json.starts_with("\"DOTTED~"));
+    assert_eq!(serde_json::from_str::<Dotted>(&json).unwrap(), dotted);
+}
+
+#[test]
+fn test_value_and_checksum() {
+    for (tag, value) in [("TX", &b"transaction"[..]), ("", b""), ("KEY", &[0xff; 33])] {
+        let tb64 = TaggedBase64::new(tag, value).unwrap();
+        let (value_base64, checksum) = tb64.encode_value_and_checksum();
+        assert_eq!(TaggedBase64::decode_raw(&value_base64).unwrap(), value);
+        assert_eq!(checksum, checksum_of(tag, value));
+        assert_eq!(
+            TaggedBase64::from_value_and_checksum(tag, &value_base64, checksum).unwrap(),
+            tb64
+        );
+    }
+
+    let (value_base64, checksum) = TaggedBase64::new("TX", b"transaction")
+        .unwrap()
+        .encode_value_and_checksum();
+    assert!(matches!(
+        TaggedBase64::from_value_and_checksum("TX", &value_base64, checksum ^ 1),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_value_and_checksum("BLOCK", &value_base64, checksum),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::from_value_and_checksum("T X", &value_base64, checksum),
+        Err(Tb64Error::InvalidTag {
+            position: 1,
+            character: ' '
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::from_value_and_checksum("TX", "A/A", checksum),
+        Err(Tb64Error::InvalidByte { .. })
+    ));
+}
+
+#[test]
+fn test_has_tag() {
+    assert!(TaggedBase64::new("TX", b"value").unwrap().has_tag());
+    assert!(TaggedBase64::new("0", b"").unwrap().has_tag());
+    assert!(!TaggedBase64::new("", b"value").unwrap().has_tag());
+    assert!(!TaggedBase64::parse("~AA").unwrap().has_tag());
+
+    let mut tb64 = TaggedBase64::new("", b"value").unwrap();
+    tb64.set_tag("TX");
+    assert!(tb64.has_tag());
+}
+
+#[test]
+fn test_try_from_array() {
+    let key = TaggedBase64::new("KEY", &[9; 32]).unwrap();
+    let array: [u8; 32] = key.clone().try_into().unwrap();
+    assert_eq!(array, [9; 32]);
+    assert_eq!(
+        <[u8; 32]>::try_from(key.clone()).unwrap(),
+        key.value_array().unwrap()
+    );
+
+    match <[u8; 16]>::try_from(key.clone()).unwrap_err() {
+        Tb64Error::WrongLength { expected, actual } => assert_eq!((expected, actual), (16, 32)),
+        e => panic!("unexpected error: {e}"),
+    }
+    match <[u8; 33]>::try_from(key).unwrap_err() {
+        Tb64Error::WrongLength { expected, actual } => assert_eq!((expected, actual), (33, 32)),
+        e => panic!("unexpected error: {e}"),
+    }
+
+    let empty = TaggedBase64::new("KEY", b"").unwrap();
+    assert_eq!(<[u8; 0]>::try_from(empty).unwrap(), [0u8; 0]);
+}
+
+#[test]
+fn test_url_safe_alphabet() {
+    let chars: std::collections::BTreeSet<char> = URL_SAFE_ALPHABET.chars().collect();
+    assert_eq!(URL_SAFE_ALPHABET.len(), 64);
+    assert_eq!(chars.len(), 64);
+    assert!(!chars.contains(&TB64_DELIM));
+    assert!(URL_SAFE_ALPHABET.is_ascii());
+
+    // The alphabet is exactly the characters allowed in tags, in the order
+    // of their base64 values.
+    for c in URL_SAFE_ALPHABET.chars() {
+        assert!(TaggedBase64::is_safe_base64_ascii(c));
+    }
+    for b in 0..=127u8 {
+        assert_eq!(
+            TaggedBase64::is_safe_base64_ascii(b as char),
+            chars.contains(&(b as char))
+        );
+    }
+    for (i, c) in URL_SAFE_ALPHABET.chars().enumerate() {
+        assert_eq!(BASE64.decode(format!("{c}A")).unwrap(), [(i as u8) << 2]);
+    }
+}
+
+#[test]
+fn test_parse_checksum_first() {
+    for (tag, value) in [("EXT", &b"external"[..]), ("", b""), ("EXT", &[1, 2])] {
+        let mut bytes = vec![checksum_of(tag, value)];
+        bytes.extend_from_slice(value);
+        let s = format!("{tag}~{}", TaggedBase64::encode_raw(&bytes));
+
+        let parsed = TaggedBase64::parse_checksum_first(&s).unwrap();
+        assert_eq!(parsed, TaggedBase64::new(tag, value).unwrap());
+        if !value.is_empty() {
+            assert!(TaggedBase64::parse(&s).is_err());
+        }
+    }
+
+    // The default order is not accepted.
+    let s = TaggedBase64::new("EXT", b"external").unwrap().to_string();
+    assert!(matches!(
+        TaggedBase64::parse_checksum_first(&s),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_checksum_first("EXT~"),
+        Err(Tb64Error::MissingChecksum)
+    ));
+}
+
+#[test]
+fn test_cached_display() {
+    use std::borrow::Cow;
+
+    let tb64 = TaggedBase64::new("TX", b"transaction").unwrap();
+    let expected = tb64.to_string();
+
+    let cached = CachedTaggedBase64::new(tb64.clone());
+    assert!(cached.is_cached());
+    assert!(matches!(cached.display(), Cow::Borrowed(s) if s == expected));
+    assert_eq!(cached.to_string(), expected);
+
+    let uncached = CachedTaggedBase64::uncached(tb64.clone());
+    assert!(!uncached.is_cached());
+    assert!(matches!(uncached.display(), Cow::Owned(s) if s == expected));
+    assert_eq!(uncached.to_string(), expected);
+
+    // The wrapper gives read access to the value.
+    assert_eq!(cached.tag(), "TX");
+    assert_eq!(*cached, tb64);
+    assert_eq!(uncached.into_inner(), tb64);
+}
+
+/// Measures how often the checksum fails to detect corruption, using a
+/// seeded RNG so that the result is deterministic.
+#[test]
+fn test_checksum_collision_rate() {
+    use rand_chacha::{
+        rand_core::{RngCore, SeedableRng},
+        ChaChaRng,
+    };
+
+    const TRIALS: usize = 100_000;
+    let mut rng = ChaChaRng::from_seed([7; 32]);
+    let random_tag = |rng: &mut ChaChaRng| -> String {
+        let alphabet = URL_SAFE_ALPHABET.as_bytes();
+        (0..rng.next_u32() % 8)
+            .map(|_| alphabet[rng.next_u32() as usize % 64] as char)
+            .collect()
+    };
+
+    // A CRC-8 detects every error confined to 8 consecutive bits, so
+    // changing a single byte of the value never goes undetected.
+    let mut single_byte_collisions = 0;
+    for _ in 0..TRIALS {
+        let tag = random_tag(&mut rng);
+        let mut value = vec![0; 1 + rng.next_u32() as usize % 64];
+        rng.fill_bytes(&mut value);
+        let checksum = checksum_of(&tag, &value);
+
+        let i = rng.next_u32() as usize % value.len();
+        value[i] ^= 1 + (rng.next_u32() % 255) as u8;
+        if checksum_of(&tag, &value) == checksum {
+            single_byte_collisions += 1;
+        }
+    }
+    assert_eq!(single_byte_collisions, 0);
+
+    // Unrelated values of the same length collide at about the rate of
+    // 1/256 expected of an 8-bit checksum. The bounds are more than 6
+    // standard deviations from the expected count of 390.
+    let mut random_collisions = 0;
+    for _ in 0..TRIALS {
+        let tag = random_tag(&mut rng);
+        let mut a = vec![0; 1 + rng.next_u32() as usize % 64];
+        let mut b = vec![0; a.len()];
+        rng.fill_bytes(&mut a);
+        rng.fill_bytes(&mut b);
+        if a != b && checksum_of(&tag, &a) == checksum_of(&tag, &b) {
+            random_collisions += 1;
+        }
+    }
+    println!(
+        "collision rate for random values: {} in {} ({:.5}, 8-bit bound {:.5})",
+        random_collisions,
+        TRIALS,
+        random_collisions as f64 / TRIALS as f64,
+        1.0 / 256.0
+    );
+    assert!(
+        (270..510).contains(&random_collisions),
+        "{random_collisions} collisions in {TRIALS} trials"
+    );
+}
+
+#[test]
+fn test_parse_unquoted() {
+    let tb64 = TaggedBase64::new("KEY", b"value").unwrap();
+    let s = tb64.to_string();
+    for quoted in [s.clone(), format!("\"{s}\""), format!("“{s}”")] {
+        assert_eq!(TaggedBase64::parse_unquoted(&quoted).unwrap(), tb64);
+    }
+    // Pasting from JSON works.
+    let json = serde_json::to_string(&tb64).unwrap();
+    assert_eq!(TaggedBase64::parse_unquoted(&json).unwrap(), tb64);
+
+    // Only one matching pair is removed.
+    for bad in [
+        format!("\"\"{s}\"\""),
+        format!("\"{s}"),
+        format!("{s}\""),
+        format!("“{s}\""),
+        format!("”{s}“"),
+    ] {
+        assert!(TaggedBase64::parse_unquoted(&bad).is_err(), "{bad}");
+    }
+
+    // The regular parser is still strict.
+    assert!(TaggedBase64::parse(&format!("\"{s}\"")).is_err());
+}
+
+#[test]
+fn test_fingerprint() {
+    let tb64 = TaggedBase64::new("KEY", b"value").unwrap();
+    let fingerprint = tb64.fingerprint();
+    assert_eq!(fingerprint.len(), 6);
+    assert!(fingerprint.bytes().all(|b| b.is_ascii_digit()));
+    // Deterministic.
+    assert_eq!(fingerprint, tb64.fingerprint());
+    assert_eq!(
+        fingerprint,
+        TaggedBase64::parse(&tb64.to_string())
+            .unwrap()
+            .fingerprint()
+    );
+
+    // Different values usually have different fingerprints.
+    let fingerprints: std::collections::BTreeSet<String> = (0..1000u32)
+        .map(|i| {
+            TaggedBase64::new("KEY", &i.to_le_bytes())
+                .unwrap()
+                .fingerprint()
+        })
+        .collect();
+    assert!(fingerprints.len() > 990);
+    // The checksum covers the tag, so the fingerprint depends on it too.
+    assert_ne!(
+        TaggedBase64::new("TX", b"value").unwrap().fingerprint(),
+        fingerprint
+    );
+}
+
+#[test]
+fn test_parse_in_set() {
+    const ALLOWED: &[&str] = &["TX", "BLOCK", ""];
+    for tag in ALLOWED {
+        let tb64 = TaggedBase64::new(tag, b"payload").unwrap();
+        assert_eq!(
+            TaggedBase64::parse_in_set(&tb64.to_string(), ALLOWED).unwrap(),
+            tb64
+        );
+    }
+
+    // Valid, but not in the set.
+    let other = TaggedBase64::new("OTHER", b"payload").unwrap().to_string();
+    assert!(TaggedBase64::parse(&other).is_ok());
+    assert!(matches!(
+        TaggedBase64::parse_in_set(&other, ALLOWED),
+        Err(Tb64Error::UnexpectedTag)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_in_set(&other, &[]),
+        Err(Tb64Error::UnexpectedTag)
+    ));
+
+    // Malformed.
+    assert!(matches!(
+        TaggedBase64::parse_in_set("TX", ALLOWED),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_in_set("TX~AAAA", ALLOWED),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_in_set("T X~AAAA", &["T X"]),
+        Err(Tb64Error::InvalidTag {
+            position: 1,
+            character: ' '
+        })
+    ));
+}
+
+#[test]
+fn test_to_string_single_buffer() {
+    for len in 0..100 {
+        let value: Vec<u8> = (0..len as u8).collect();
+        let tb64 = TaggedBase64::new("TAG", &value).unwrap();
+        let s = tagged_base64::to_string(&tb64);
+        assert_eq!(s, format!("{tb64}"));
+        assert_eq!(s, String::from(&tb64));
+        assert_eq!(s.len(), tb64.encoded_len());
+        assert!(s.capacity() >= s.len());
+        assert_eq!(TaggedBase64::parse(&s).unwrap(), tb64);
+    }
+}
+
+#[test]
+fn test_semantic_eq() {
+    let a = TaggedBase64::new("TAG", b"value").unwrap();
+    let parsed = TaggedBase64::parse(&a.to_string()).unwrap();
+    assert!(a.semantic_eq(&parsed));
+    assert_eq!(a, parsed);
+
+    // A deliberately mismatched checksum: `==` sees the difference, but
+    // `semantic_eq` does not.
+    #[cfg(feature = "test-util")]
+    {
+        let drifted =
+            TaggedBase64::new_with_raw_checksum("TAG", b"value", a.clone().into_parts().2 ^ 1)
+                .unwrap();
+        assert_ne!(a, drifted);
+        assert!(a.semantic_eq(&drifted));
+        assert!(drifted.semantic_eq(&a));
+    }
+
+    // Differences in tag or value are still significant.
+    assert!(!a.semantic_eq(&TaggedBase64::new("OTHER", b"value").unwrap()));
+    assert!(!a.semantic_eq(&TaggedBase64::new("TAG", b"other").unwrap()));
+}
+
+#[test]
+fn test_to_path_segment() {
+    // RFC 3986 unreserved characters, which are never percent-encoded.
+    fn is_unreserved(c: char) -> bool {
+        c.is_ascii_alphanumeric() || "-._~".contains(c)
+    }
+
+    let tags = ["", "TX", "a-b_c", "0123456789"];
+    for tag in tags {
+        for len in 0..50u8 {
+            let value: Vec<u8> = (0..len).map(|i| i.wrapping_mul(97)).collect();
+            let tb64 = TaggedBase64::new(tag, &value).unwrap();
+            let segment = tb64.to_path_segment();
+            assert_eq!(segment, tb64.to_string());
+            assert!(segment.chars().all(is_unreserved), "{segment}");
+            assert_eq!(TaggedBase64::parse(&segment).unwrap(), tb64);
+        }
+    }
+}
+
+#[test]
+fn test_standard_b64() {
+    for len in 0..40u8 {
+        let value: Vec<u8> = (0..len).map(|i| 0xf8 | i).collect();
+        let tb64 = TaggedBase64::new("TAG", &value).unwrap();
+        let standard = tb64.to_string_standard_b64();
+        assert!(standard.starts_with("TAG~"));
+        assert_eq!(standard.len() % 4, "TAG~".len() % 4);
+        assert_eq!(TaggedBase64::parse_standard_b64(&standard).unwrap(), tb64);
+        // The two forms differ only in the alphabet and padding.
+        assert_eq!(
+            standard
+                .trim_end_matches('=')
+                .replace('+', "-")
+                .replace('/', "_"),
+            tb64.to_string()
+        );
+    }
+
+    // High bytes use the characters which differ between the alphabets.
+    let tb64 = TaggedBase64::new("TAG", &[0xfb, 0xff, 0xbf]).unwrap();
+    let standard = tb64.to_string_standard_b64();
+    assert!(standard.contains('+') || standard.contains('/'));
+    assert!(TaggedBase64::parse(&standard).is_err());
+    assert!(TaggedBase64::parse_standard_b64(&tb64.to_string()).is_err());
+
+    assert!(matches!(
+        TaggedBase64::parse_standard_b64("TAG~AAAA"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_standard_b64("TAG"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+}
+
+#[test]
+fn test_serialize_cached() {
+    let values: Vec<TaggedBase64> = (0..20u8)
+        .map(|len| TaggedBase64::new("TAG", &vec![len; len as usize]).unwrap())
+        .collect();
+    let cached: Vec<CachedTaggedBase64> = values
+        .iter()
+        .cloned()
+        .map(CachedTaggedBase64::new)
+        .collect();
+    let uncached: Vec<CachedTaggedBase64> = values
+        .iter()
+        .cloned()
+        .map(CachedTaggedBase64::uncached)
+        .collect();
+
+    let json = serde_json::to_string(&values).unwrap();
+    assert_eq!(serde_json::to_string(&cached).unwrap(), json);
+    assert_eq!(serde_json::to_string(&uncached).unwrap(), json);
+    assert_eq!(
+        serde_json::from_str::<Vec<TaggedBase64>>(&json).unwrap(),
+        values
+    );
+    for tb64 in &values {
+        assert_eq!(
+            serde_json::to_value(tb64).unwrap(),
+            serde_json::Value::String(tb64.to_string())
+        );
+    }
+
+    // Binary formats are unaffected by the cache.
+    let bin = bincode::serialize(&values).unwrap();
+    assert_eq!(bincode::serialize(&cached).unwrap(), bin);
+    assert_eq!(bincode::serialize(&uncached).unwrap(), bin);
+}
+
+/// A smoke test for the parse benchmark: a value of a megabyte parses
+/// promptly and round trips.
+#[test]
+fn test_parse_large_value() {
+    let value: Vec<u8> = (0..1 << 20).map(|i: u32| (i * 7 + 3) as u8).collect();
+    let s = TaggedBase64::new("BENCH", &value).unwrap().to_string();
+    let start = std::time::Instant::now();
+    let tb64 = TaggedBase64::parse(&s).unwrap();
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    assert_eq!(tb64.value(), value);
+}
+
+#[test]
+fn test_validate_all() {
+    let a = TaggedBase64::new("TX", b"first").unwrap();
+    let b = TaggedBase64::new("BLOCK", b"second").unwrap();
+    let c = TaggedBase64::new("", b"").unwrap();
+    let input = [
+        a.to_string(),
+        "TX".to_string(),
+        b.to_string(),
+        String::new(),
+        "T X~AAAA".to_string(),
+        c.to_string(),
+        "TX~AAAA".to_string(),
+        format!("{}\r", b),
+    ]
+    .join("\n")
+        + "\n";
+
+    let parsed: Vec<_> = TaggedBase64::parse_many(&input).collect();
+    assert_eq!(parsed.len(), 8);
+    assert_eq!(parsed[0].as_ref().unwrap(), &a);
+    assert_eq!(parsed[2].as_ref().unwrap(), &b);
+    assert_eq!(parsed[5].as_ref().unwrap(), &c);
+    // A CRLF line ending is accepted.
+    assert_eq!(parsed[7].as_ref().unwrap(), &b);
+
+    let errors = TaggedBase64::validate_all(&input);
+    assert_eq!(
+        errors.iter().map(|(line, _)| *line).collect::<Vec<_>>(),
+        [1, 3, 4, 6]
+    );
+    assert!(matches!(errors[0].1, Tb64Error::MissingDelimiter));
+    assert!(matches!(errors[1].1, Tb64Error::MissingDelimiter));
+    assert!(matches!(errors[2].1, Tb64Error::InvalidTag { .. }));
+    assert!(matches!(errors[3].1, Tb64Error::InvalidChecksum));
+
+    let valid = format!("{a}\n{b}\n{c}");
+    assert!(TaggedBase64::validate_all(&valid).is_empty());
+    assert!(TaggedBase64::validate_all("").is_empty());
+}
+
+#[test]
+fn test_compressed() {
+    let mut sparse = vec![0u8; 4096];
+    sparse[100] = 1;
+    sparse[3000] = 0xff;
+    let incompressible: Vec<u8> = (0..=255).collect();
+    let long_run = vec![7u8; 1000];
+    let inputs: [&[u8]; 7] = [
+        b"",
+        b"a",
+        b"aab",
+        &sparse,
+        &incompressible,
+        &long_run,
+        b"abcabcabc",
+    ];
+    for value in inputs {
+        let compressed = TaggedBase64::new_compressed("BLOB", value).unwrap();
+        // Never more than one byte longer than the original.
+        assert!(compressed.value().len() <= value.len() + 1);
+        assert_eq!(compressed.decompressed_value().unwrap(), value);
+
+        let s = compressed.to_string();
+        assert_eq!(TaggedBase64::parse(&s).unwrap(), compressed);
+        assert_eq!(
+            TaggedBase64::parse_compressed(&s).unwrap(),
+            TaggedBase64::new("BLOB", value).unwrap()
+        );
+    }
+
+    let compressed = TaggedBase64::new_compressed("BLOB", &sparse).unwrap();
+    assert_eq!(compressed.value()[0], COMPRESSION_RLE);
+    assert!(
+        compressed.to_string().len()
+            < TaggedBase64::new("BLOB", &sparse)
+                .unwrap()
+                .to_string()
+                .len()
+    );
+    let stored = TaggedBase64::new_compressed("BLOB", &incompressible).unwrap();
+    assert_eq!(stored.value()[0], COMPRESSION_NONE);
+
+    // Malformed compressed values.
+    for bad in [
+        &[][..],
+        &[2],
+        &[COMPRESSION_RLE, 3],
+        &[COMPRESSION_RLE, 0, 5],
+    ] {
+        let s = TaggedBase64::new("BLOB", bad).unwrap().to_string();
+        assert!(matches!(
+            TaggedBase64::parse_compressed(&s),
+            Err(Tb64Error::InvalidData)
+        ));
+    }
+}
+
+#[tagged("COMMIT", const_tag)]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+struct Commit(Vec<u8>);
+
+#[tagged("SIG", const_tag, len = 64)]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+struct Sig(Vec<u8>);
+
+/// A type with its own `TAG`, which `tagged` without `const_tag` leaves alone.
+#[tagged("VERSIONED")]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+struct Versioned(Vec<u8>);
+
+impl Versioned {
+    const TAG: u8 = 2;
+}
+
+/// The tags of a closed set of types, built at compile time.
+const KNOWN_TAGS: [&str; 3] = [Commit::TAG, Sig::TAG, Light::TAG];
+
+#[test]
+fn test_const_tag() {
+    assert_eq!(Commit::TAG, Commit::tag());
+    assert_eq!(Sig::TAG, <Sig as Tagged>::tag());
+    assert_eq!(<Sig as Tagged>::expected_len(), Some(64));
+    assert_eq!(Light::TAG, Light::tag());
+    assert_eq!(GenericLight::<u8>::TAG, "GENERIC");
+    assert_eq!(Versioned::TAG, 2);
+    assert_eq!(Versioned::tag(), "VERSIONED");
+
+    // Tags can size and index arrays at compile time.
+    const COMMIT_TAG_LEN: usize = Commit::TAG.len();
+    let buf = [0u8; COMMIT_TAG_LEN];
+    assert_eq!(buf.len(), 6);
+    const FIRST: &str = KNOWN_TAGS[0];
+    assert_eq!(FIRST, "COMMIT");
+
+    fn kind(tb64: &TaggedBase64) -> Option<usize> {
+        match tb64.tag().as_str() {
+            Commit::TAG => Some(0),
+            Sig::TAG => Some(1),
+            tag if tag == Light::TAG => Some(2),
+            _ => None,
+        }
+    }
+    for (i, tag) in KNOWN_TAGS.iter().enumerate() {
+        let tb64 = TaggedBase64::new(tag, b"").unwrap();
+        assert_eq!(kind(&tb64), Some(i));
+    }
+    assert_eq!(kind(&TaggedBase64::new("OTHER", b"").unwrap()), None);
+}
+
+#[test]
+fn test_parse_reporting() {
+    let tb64 = TaggedBase64::new("TAG", b"some value").unwrap();
+    let checksum = checksum_of("TAG", b"some value");
+    let (res, report) = TaggedBase64::parse_reporting(&tb64.to_string());
+    assert_eq!(res.unwrap(), tb64);
+    assert_eq!(report, None);
+
+    // Corrupt the checksum byte.
+    let mut bytes = b"some value".to_vec();
+    bytes.push(checksum ^ 0x5a);
+    let corrupted = format!("TAG~{}", TaggedBase64::encode_raw(&bytes));
+    let (res, report) = TaggedBase64::parse_reporting(&corrupted);
+    assert!(matches!(res, Err(Tb64Error::InvalidChecksum)));
+    assert_eq!(report, Some((checksum, checksum ^ 0x5a)));
+
+    // Corrupt the value, keeping the checksum.
+    let mut bytes = b"some valuE".to_vec();
+    bytes.push(checksum);
+    let corrupted = format!("TAG~{}", TaggedBase64::encode_raw(&bytes));
+    let (res, report) = TaggedBase64::parse_reporting(&corrupted);
+    assert!(matches!(res, Err(Tb64Error::InvalidChecksum)));
+    assert_eq!(report, Some((checksum_of("TAG", b"some valuE"), checksum)));
+
+    // Structural failures have no checksums to report.
+    for (s, err) in [
+        ("TAG", Tb64Error::MissingDelimiter),
+        ("TAG~", Tb64Error::MissingChecksum),
+        (
+            "T X~AAAA",
+            Tb64Error::InvalidTag {
+                position: 1,
+                character: ' ',
+            },
+        ),
+    ] {
+        let (res, report) = TaggedBase64::parse_reporting(s);
+        assert_eq!(
+            core::mem::discriminant(&res.unwrap_err()),
+            core::mem::discriminant(&err)
+        );
+        assert_eq!(report, None);
+    }
+    let (res, report) = TaggedBase64::parse_reporting("TAG~A!AA");
+    assert!(matches!(res, Err(Tb64Error::InvalidByte { .. })));
+    assert_eq!(report, None);
+}
+
+#[test]
+fn test_from_chunks() {
+    let value: Vec<u8> = (0..=255).collect();
+    let expected = TaggedBase64::new("TAG", &value).unwrap();
+
+    for size in [1, 3, 7, 64, 256, 1000] {
+        let chunked = TaggedBase64::from_chunks("TAG", value.chunks(size)).unwrap();
+        assert_eq!(chunked, expected);
+    }
+    let owned: Vec<Vec<u8>> = value.chunks(10).map(<[u8]>::to_vec).collect();
+    assert_eq!(TaggedBase64::from_chunks("TAG", owned).unwrap(), expected);
+    let with_empty = [&value[..100], &[], &value[100..]];
+    assert_eq!(
+        TaggedBase64::from_chunks("TAG", with_empty).unwrap(),
+        expected
+    );
+
+    assert_eq!(
+        TaggedBase64::from_chunks("TAG", core::iter::empty::<&[u8]>()).unwrap(),
+        TaggedBase64::new("TAG", b"").unwrap()
+    );
+    assert!(matches!(
+        TaggedBase64::from_chunks("T~G", value.chunks(3)),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+}
+
+#[test]
+fn test_to_string_percent_safe() {
+    for tag in ["", "TX", "a-b_c"] {
+        for len in 0..20u8 {
+            let value: Vec<u8> = (0..len).map(|i| i.wrapping_mul(151)).collect();
+            let tb64 = TaggedBase64::new(tag, &value).unwrap();
+            let s = tb64.to_string_percent_safe();
+            assert_eq!(s, format!("{tag}%7E{}", &tb64.to_string()[tag.len() + 1..]));
+            assert!(!s.contains('~'));
+            assert_eq!(TaggedBase64::parse_url(&s).unwrap(), tb64);
+            assert!(TaggedBase64::parse(&s).is_err());
+        }
+    }
+}
+
+#[test]
+fn test_distinct_tags() {
+    let batch = [
+        TaggedBase64::new("TX", b"1").unwrap(),
+        TaggedBase64::new("BLOCK", b"2").unwrap(),
+        TaggedBase64::new("TX", b"3").unwrap(),
+        TaggedBase64::new("", b"4").unwrap(),
+        TaggedBase64::new("ACCOUNT", b"5").unwrap(),
+        TaggedBase64::new("BLOCK", b"6").unwrap(),
+        TaggedBase64::new("", b"7").unwrap(),
+    ]
+    .iter()
+    .map(|tb64| tb64.to_string())
+    .collect::<Vec<_>>()
+    .join("\n");
+    assert_eq!(
+        TaggedBase64::distinct_tags(&batch).unwrap(),
+        ["TX", "BLOCK", "", "ACCOUNT"]
+    );
+    assert!(TaggedBase64::distinct_tags("").unwrap().is_empty());
+
+    let bad = format!("{batch}\nTX~AAAA");
+    assert!(matches!(
+        TaggedBase64::distinct_tags(&bad),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+}
+
+#[test]
+fn test_from_base64() {
+    for len in 0..30u8 {
+        let value: Vec<u8> = (0..len).map(|i| i.wrapping_mul(37)).collect();
+        let encoded = TaggedBase64::encode_raw(&value);
+        let tb64 = TaggedBase64::from_base64("TAG", &encoded).unwrap();
+        assert_eq!(tb64, TaggedBase64::new("TAG", &value).unwrap());
+        assert_eq!(tb64.value(), value);
+        assert_eq!(
+            TaggedBase64::parse_add_checksum("TAG", &encoded).unwrap(),
+            tb64
+        );
+        // The display form has the checksum appended to the value.
+        let s = tb64.to_string();
+        assert_eq!(TaggedBase64::parse(&s).unwrap(), tb64);
+        let (_, b64) = s.split_once('~').unwrap();
+        assert_eq!(
+            TaggedBase64::from_base64("TAG", b64).unwrap().value().len(),
+            value.len() + 1
+        );
+    }
+
+    assert!(matches!(
+        TaggedBase64::from_base64("TAG", "A!"),
+        Err(Tb64Error::InvalidByte { .. })
+    ));
+    assert!(matches!(
+        TaggedBase64::from_base64("T~G", "AAAA"),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+}
+
+#[test]
+fn test_map_keys() {
+    use std::collections::BTreeMap;
+
+    let keys = [
+        TaggedBase64::new("TX", b"b").unwrap(),
+        TaggedBase64::new("TX", b"a").unwrap(),
+        TaggedBase64::new("BLOCK", b"z").unwrap(),
+        TaggedBase64::new("", b"").unwrap(),
+    ];
+    let map: BTreeMap<TaggedBase64, u32> = keys.iter().cloned().zip(0..).collect();
+    // Ordered by tag, then value.
+    assert_eq!(
+        map.keys().cloned().collect::<Vec<_>>(),
+        [
+            keys[3].clone(),
+            keys[2].clone(),
+            keys[1].clone(),
+            keys[0].clone()
+        ]
+    );
+
+    let json = serde_json::to_value(&map).unwrap();
+    let object = json.as_object().unwrap();
+    assert_eq!(object.len(), keys.len());
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(object[&key.to_string()], i);
+    }
+    let s = serde_json::to_string(&map).unwrap();
+    assert_eq!(
+        serde_json::from_str::<BTreeMap<TaggedBase64, u32>>(&s).unwrap(),
+        map
+    );
+    assert_eq!(
+        serde_json::from_value::<BTreeMap<TaggedBase64, u32>>(json).unwrap(),
+        map
+    );
+
+    // An invalid key is rejected.
+    assert!(serde_json::from_str::<BTreeMap<TaggedBase64, u32>>(r#"{"TX~AAAA": 1}"#).is_err());
+}
+
+#[test]
+fn test_map_tag() {
+    let tb64 = TaggedBase64::new("TX", b"payload").unwrap();
+
+    let prefixed = tb64.clone().map_tag(|tag| format!("ESP-{tag}")).unwrap();
+    assert_eq!(prefixed.tag(), "ESP-TX");
+    assert_eq!(prefixed.value(), b"payload");
+    assert_eq!(prefixed, TaggedBase64::new("ESP-TX", b"payload").unwrap());
+    assert_eq!(
+        TaggedBase64::parse(&prefixed.to_string()).unwrap(),
+        prefixed
+    );
+
+    let lower = tb64.clone().map_tag(|tag| tag.to_lowercase()).unwrap();
+    assert_eq!(lower.to_string(), tb64.to_string_lower_tag());
+
+    assert!(matches!(
+        tb64.clone().map_tag(|tag| format!("ns~{tag}")),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+    assert!(matches!(
+        tb64.map_tag(|tag| format!("ns {tag}")),
+        Err(Tb64Error::InvalidTag {
+            position: 2,
+            character: ' '
+        })
+    ));
+}
+
+#[cfg(all(feature = "build-cli", not(target_arch = "wasm32")))]
+#[test]
+fn test_cli_tag_env() {
+    let value = b"some raw data";
+    let (ok, encoded) = run_cli(
+        &["--tag-env", "TB64_TEST_TAG"],
+        &[("TB64_TEST_TAG", "SECRET")],
+        value,
+    );
+    assert!(ok);
+    let expected = TaggedBase64::new("SECRET", value).unwrap();
+    assert_eq!(String::from_utf8(encoded).unwrap(), format!("{expected}\n"));
+
+    // The variable must be set, and the error goes to stderr.
+    assert!(std::env::var_os("TB64_TEST_UNSET").is_none());
+    let (ok, out) = run_cli(&["--tag-env", "TB64_TEST_UNSET"], &[], value);
+    assert!(!ok);
+    assert!(out.is_empty());
+    // It cannot be combined with --tag.
+    let (ok, _) = run_cli(
+        &["--tag", "RAW", "--tag-env", "TB64_TEST_TAG"],
+        &[("TB64_TEST_TAG", "SECRET")],
+        value,
+    );
+    assert!(!ok);
+}
+
+#[test]
+fn test_tuple_conversions() {
+    for tag in ["", "TX", "a-b_c"] {
+        let tb64 = TaggedBase64::new(tag, b"tuple value").unwrap();
+        let tuple: (String, Vec<u8>) = tb64.clone().into();
+        assert_eq!(tuple, (tag.to_string(), b"tuple value".to_vec()));
+        assert_eq!(TaggedBase64::try_from(tuple).unwrap(), tb64);
+    }
+
+    let tuple = ("TX".to_string(), Vec::new());
+    let tb64: TaggedBase64 = tuple.try_into().unwrap();
+    assert_eq!(tb64, TaggedBase64::new("TX", b"").unwrap());
+
+    assert!(matches!(
+        TaggedBase64::try_from(("T~X".to_string(), vec![1, 2, 3])),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::try_from(("T X".to_string(), vec![1, 2, 3])),
+        Err(Tb64Error::InvalidTag {
+            position: 1,
+            character: ' '
+        })
+    ));
+}
+
+#[test]
+fn test_parse_long_invalid_prefix() {
+    // A long string with an invalid tag character near the start and the
+    // delimiter at the very end is rejected at the invalid character.
+    let mut s = "AB CD".to_string();
+    s.push_str(&"A".repeat(1 << 20));
+    s.push('~');
+    assert!(matches!(
+        TaggedBase64::parse(&s),
+        Err(Tb64Error::InvalidTag {
+            position: 2,
+            character: ' '
+        })
+    ));
+    // Without any delimiter, the invalid tag is still reported first.
+    assert!(matches!(
+        TaggedBase64::parse(&s[..s.len() - 1]),
+        Err(Tb64Error::InvalidTag {
+            position: 2,
+            character: ' '
+        })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse("AΣB"),
+        Err(Tb64Error::NonAsciiTag {
+            position: 1,
+            character: 'Σ'
+        })
+    ));
+
+    // Valid tags, and strings of only tag characters, behave as before.
+    assert!(matches!(
+        TaggedBase64::parse(&"A".repeat(1 << 20)),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    let tb64 = TaggedBase64::new("TAG", b"value").unwrap();
+    assert_eq!(TaggedBase64::parse(&tb64.to_string()).unwrap(), tb64);
+    assert!(matches!(
+        TaggedBase64::parse_with_policy("TA-G~AAAA", &TagPolicy::strict_alnum()),
+        Err(Tb64Error::InvalidTag {
+            position: 2,
+            character: '-'
+        })
+    ));
+}
+
+/// A type which only gets `Hash` and `Eq` from the tagged macro.
+#[tagged("HFIELD", hash_by_bytes)]
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+struct HashedField(ark_bls12_381::Fr);
+
+#[test]
+fn test_hash_by_bytes() {
+    use ark_std::UniformRand;
+    use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+    use std::collections::HashMap;
+
+    let mut rng = ChaChaRng::from_seed([7; 32]);
+    let fields: Vec<HashedField> = (0..10)
+        .map(|_| HashedField(ark_bls12_381::Fr::rand(&mut rng)))
+        .collect();
+
+    let map: HashMap<HashedField, usize> = fields.iter().cloned().zip(0..).collect();
+    assert_eq!(map.len(), fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        assert_eq!(map[field], i);
+        // A copy which went through the tagged representation is the same key.
+        let copy: HashedField = field.to_string().parse().unwrap();
+        assert_eq!(&copy, field);
+        assert_eq!(map[&copy], i);
+    }
+    assert_ne!(fields[0], fields[1]);
+}
+
+#[test]
+fn test_parse_exact() {
+    let tb64 = TaggedBase64::new("TAG", b"exact").unwrap();
+    let s = tb64.to_string();
+    assert_eq!(TaggedBase64::parse_exact(&s).unwrap(), tb64);
+
+    for (trailing, offset) in [
+        (" ", 0),
+        ("\n", 0),
+        ("\r\n", 0),
+        ("~AAAA", 0),
+        (&format!("~{}", &s[4..]), 0),
+        ("=", 0),
+        ("A B", 1),
+    ] {
+        let input = format!("{s}{trailing}");
+        assert!(TaggedBase64::parse(&input).is_err());
+        let err = TaggedBase64::parse_exact(&input).unwrap_err();
+        assert!(
+            matches!(err, Tb64Error::TrailingData { position } if position == s.len() + offset),
+            "{input:?}: {err:?}"
+        );
+    }
+
+    // Other errors are reported as by `parse`.
+    assert!(matches!(
+        TaggedBase64::parse_exact("TAG"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_exact("TAG~AAAA"),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_exact("TAG~ AAAA"),
+        Err(Tb64Error::TrailingData { position: 4 })
+    ));
+}
+
+/// 64-bit FNV-1a, as a minimal [digest::Digest] for testing.
+#[cfg(feature = "digest")]
+#[derive(Clone)]
+struct Fnv64(u64);
+
+#[cfg(feature = "digest")]
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+#[cfg(feature = "digest")]
+impl digest::HashMarker for Fnv64 {}
+
+#[cfg(feature = "digest")]
+impl digest::OutputSizeUser for Fnv64 {
+    type OutputSize = digest::consts::U8;
+}
+
+#[cfg(feature = "digest")]
+impl digest::Update for Fnv64 {
+    fn update(&mut self, data: &[u8]) {
+        for b in data {
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(feature = "digest")]
+impl digest::FixedOutput for Fnv64 {
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        out.copy_from_slice(&self.0.to_be_bytes());
+    }
+}
+
+#[cfg(feature = "digest")]
+#[test]
+fn test_value_hash() {
+    let tb64 = TaggedBase64::new("TAG", b"hello").unwrap();
+    let expected = 0xa430d84680aabd0bu64.to_be_bytes();
+    assert_eq!(tb64.value_hash::<Fnv64>().as_slice(), expected);
+
+    // Only the value is hashed.
+    let other = TaggedBase64::new("OTHER", b"hello").unwrap();
+    assert_eq!(other.value_hash::<Fnv64>(), tb64.value_hash::<Fnv64>());
+    let different = TaggedBase64::new("TAG", b"hellO").unwrap();
+    assert_ne!(different.value_hash::<Fnv64>(), tb64.value_hash::<Fnv64>());
+}
+
+#[test]
+fn test_parse_reuse() {
+    let values: Vec<TaggedBase64> = (0..50u8)
+        .map(|i| {
+            TaggedBase64::new(
+                if i % 2 == 0 { "EVEN" } else { "ODD" },
+                &vec![i; i as usize],
+            )
+            .unwrap()
+        })
+        .collect();
+    let strings: Vec<String> = values.iter().map(|tb64| tb64.to_string()).collect();
+
+    let mut buf = Vec::with_capacity(100);
+    let ptr = buf.as_ptr();
+    for (s, tb64) in strings.iter().zip(&values) {
+        let tag = TaggedBase64::parse_reuse(s, &mut buf).unwrap();
+        assert_eq!(tag, tb64.tag());
+        assert_eq!(buf, tb64.value());
+        // The buffer was big enough for every value, so it was never
+        // reallocated.
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(buf.capacity(), 100);
+    }
+
+    // The buffer is cleared, not appended to.
+    buf.extend_from_slice(b"stale");
+    TaggedBase64::parse_reuse(&strings[3], &mut buf).unwrap();
+    assert_eq!(buf, values[3].value());
+
+    assert!(matches!(
+        TaggedBase64::parse_reuse("TAG~AAAA", &mut buf),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_reuse("TAG", &mut buf),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_reuse("TAG~A!", &mut buf),
+        Err(Tb64Error::InvalidByte { .. })
+    ));
+}
+
+#[test]
+fn test_dissect() {
+    let checksum = checksum_of("TAG", b"hello");
+    let mut bytes = b"hello".to_vec();
+    bytes.push(checksum);
+    let value_b64 = TaggedBase64::encode_raw(&bytes);
+    let s = format!("TAG~{value_b64}");
+    assert_eq!(s, TaggedBase64::new("TAG", b"hello").unwrap().to_string());
+
+    assert_eq!(
+        TaggedBase64::dissect(&s).unwrap(),
+        Dissection {
+            tag: "TAG",
+            value_b64: &value_b64,
+            value: b"hello".to_vec(),
+            checksum,
+            checksum_valid: true,
+        }
+    );
+
+    // A bad checksum is reported, not an error.
+    let bad = format!("OTHER~{value_b64}");
+    let dissection = TaggedBase64::dissect(&bad).unwrap();
+    assert_eq!(dissection.tag, "OTHER");
+    assert_eq!(dissection.value_b64, value_b64);
+    assert_eq!(dissection.value, b"hello");
+    assert_eq!(dissection.checksum, checksum);
+    assert!(!dissection.checksum_valid);
+
+    assert!(matches!(
+        TaggedBase64::dissect("TAG"),
+        Err(Tb64Error::MissingDelimiter)
+    ));
+    assert!(matches!(
+        TaggedBase64::dissect("TAG~A!"),
+        Err(Tb64Error::InvalidByte { .. })
+    ));
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen_test]
+fn wasm_delim() {
+    let tb64 = TaggedBase64::new("TAG", b"value").unwrap();
+    let js = JsTaggedBase64::new("TAG", b"value").unwrap();
+    for delim in [".", ":", "!"] {
+        let c = delim.chars().next().unwrap();
+        let s = js.to_string_with_delim(delim).unwrap();
+        assert_eq!(s, tb64.to_string_with_delim(c).unwrap());
+        assert_eq!(JsTaggedBase64::parse_with_delim(&s, delim).unwrap(), tb64);
+    }
+
+    // The delimiter must be a single non-base64 character.
+    for delim in ["", "..", "A", "-"] {
+        assert!(js.to_string_with_delim(delim).is_err(), "{delim}");
+        assert!(JsTaggedBase64::parse_with_delim("TAG.AAAA", delim).is_err());
+    }
+    let dotted = js.to_string_with_delim(".").unwrap();
+    assert!(JsTaggedBase64::parse_with_delim(&dotted, "~").is_err());
+}
+
+#[test]
+fn test_verify_str() {
+    // Lengths on both sides of the internal chunk size.
+    for len in (0..300).chain([500, 1000, 4096]) {
+        let value: Vec<u8> = (0..len).map(|i| (i * 13 + 5) as u8).collect();
+        let s = TaggedBase64::new("TAG", &value).unwrap().to_string();
+        TaggedBase64::verify_str(&s).unwrap();
+
+        // A different tag does not match the checksum.
+        let moved = format!("OTHER{}", &s[3..]);
+        assert!(matches!(
+            TaggedBase64::verify_str(&moved),
+            Err(Tb64Error::InvalidChecksum)
+        ));
+    }
+
+    // Every other input gets the same result as from `parse`.
+    let long = TaggedBase64::new("TAG", &[7; 600]).unwrap().to_string();
+    let mut invalid_late = long.clone();
+    invalid_late.replace_range(700..701, "!");
+    for s in [
+        "TAG",
+        "TAG~",
+        "T X~AAAA",
+        "TAG~AAAA",
+        "TAG~A",
+        "TAG~AB",
+        "TAG~A!AA",
+        "TAG~AAAA ",
+        &invalid_late,
+        &long[..long.len() - 1],
+    ] {
+        let expected = TaggedBase64::parse(s).unwrap_err();
+        let actual = TaggedBase64::verify_str(s).unwrap_err();
+        assert_eq!(format!("{actual:?}"), format!("{expected:?}"), "{s}");
+    }
+}
+
+#[quickcheck]
+fn verify_str_agrees_with_parse(value: Vec<u8>, flip: Option<(u16, u8)>) -> bool {
+    let mut s = TaggedBase64::new("TAG", &value)
+        .unwrap()
+        .to_string()
+        .into_bytes();
+    if let Some((ix, bit)) = flip {
+        let ix = ix as usize % s.len();
+        s[ix] ^= 1 << (bit % 7);
+    }
+    let s = String::from_utf8(s).unwrap();
+    format!("{:?}", TaggedBase64::verify_str(&s))
+        == format!("{:?}", TaggedBase64::parse(&s).map(|_| ()))
+}
+
+#[cfg(feature = "qr")]
+#[test]
+fn test_qr_string() {
+    // The RFC 4648 test vectors, as prefixes of the encoding, which also
+    // contains the checksum.
+    let qr = TaggedBase64::new("TAG", b"fooba").unwrap().to_qr_string();
+    assert!(qr.starts_with("TAG~MZXW6YTB"), "{qr}");
+    let qr = TaggedBase64::new("TAG", b"foob").unwrap().to_qr_string();
+    assert!(qr.starts_with("TAG~MZXW6Y"), "{qr}");
+
+    for len in 0..50u8 {
+        let value: Vec<u8> = (0..len).map(|i| i.wrapping_mul(201)).collect();
+        let tb64 = TaggedBase64::new("KEY", &value).unwrap();
+        let qr = tb64.to_qr_string();
+        let encoded = qr.strip_prefix("KEY~").unwrap();
+        assert!(encoded
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || ('2'..='7').contains(&c)));
+        assert_eq!(encoded.len(), (8 * (value.len() + 1)).div_ceil(5));
+        assert_eq!(TaggedBase64::parse_qr(&qr).unwrap(), tb64);
+    }
+
+    let qr = TaggedBase64::new("KEY", b"value").unwrap().to_qr_string();
+    assert!(matches!(
+        TaggedBase64::parse_qr(&qr.to_lowercase().replace("key", "KEY")),
+        Err(Tb64Error::InvalidByte { offset: 0, .. })
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_qr(&qr.replace("KEY", "KEX")),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+    // A leftover symbol which does not complete a byte.
+    assert!(matches!(
+        TaggedBase64::parse_qr(&format!("{qr}A")),
+        Err(Tb64Error::InvalidData) | Err(Tb64Error::InvalidChecksum)
+    ));
+    // Nonzero padding bits ("MZ" rather than "MY" for "f").
+    assert!(matches!(
+        TaggedBase64::parse_qr("KEY~MZ"),
+        Err(Tb64Error::InvalidData)
+    ));
+    assert!(matches!(
+        TaggedBase64::parse_qr("KEY~"),
+        Err(Tb64Error::MissingChecksum)
+    ));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn test_new_with_raw_checksum() {
+    let good = TaggedBase64::new("TAG", b"value").unwrap();
+    let checksum = checksum_of("TAG", b"value");
+
+    let same = TaggedBase64::new_with_raw_checksum("TAG", b"value", checksum).unwrap();
+    assert!(same.is_valid());
+    assert_eq!(same, good);
+
+    let corrupted = TaggedBase64::new_with_raw_checksum("TAG", b"value", checksum ^ 1).unwrap();
+    assert!(!corrupted.is_valid());
+    assert_ne!(corrupted, good);
+    assert!(corrupted.semantic_eq(&good));
+    // Downstream parsing rejects its string form.
+    assert!(matches!(
+        TaggedBase64::parse(&corrupted.to_string()),
+        Err(Tb64Error::InvalidChecksum)
+    ));
+
+    assert!(matches!(
+        TaggedBase64::new_with_raw_checksum("T~G", b"value", checksum),
+        Err(Tb64Error::TagContainsDelimiter)
+    ));
+}
+
+#[test]
+fn test_encode_all() {
+    let items: [(&str, &[u8]); 4] = [
+        ("TX", b"first"),
+        ("BLOCK", b""),
+        ("BAD TAG", b"third"),
+        ("", b"fourth"),
+    ];
+    let encoded: Vec<_> = encode_all(items).collect();
+    assert_eq!(encoded.len(), 4);
+    for (i, (tag, value)) in items.iter().enumerate() {
+        if i == 2 {
+            assert!(matches!(
+                encoded[i],
+                Err(Tb64Error::InvalidTag {
+                    position: 3,
+                    character: ' '
+                })
+            ));
+        } else {
+            let s = encoded[i].as_ref().unwrap();
+            assert_eq!(*s, TaggedBase64::new(tag, value).unwrap().to_string());
+        }
+    }
+
+    // The valid strings form a batch which parses back.
+    let batch = encoded
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert!(TaggedBase64::validate_all(&batch).is_empty());
+    assert_eq!(
+        TaggedBase64::distinct_tags(&batch).unwrap(),
+        ["TX", "BLOCK", ""]
+    );
+    assert_eq!(encode_all(Vec::new()).count(), 0);
+}
+
+#[test]
+fn test_infer_tag() {
+    let candidates = ["TX", "BLOCK", "ACCOUNT", "PROOF"];
+    for tag in candidates {
+        let s = TaggedBase64::new(tag, b"stored without its tag")
+            .unwrap()
+            .to_string();
+        let (_, value_b64) = s.split_once('~').unwrap();
+        assert_eq!(
+            TaggedBase64::infer_tag(value_b64, &candidates).as_deref(),
+            Some(tag)
+        );
+        // A candidate list without the right tag finds nothing here.
+        let others: Vec<&str> = candidates.iter().copied().filter(|t| *t != tag).collect();
+        assert_eq!(TaggedBase64::infer_tag(value_b64, &others), None);
+    }
+
+    assert_eq!(TaggedBase64::infer_tag("", &candidates), None);
+    assert_eq!(TaggedBase64::infer_tag("A!AA", &candidates), None);
+    assert_eq!(TaggedBase64::infer_tag("AAAA", &[]), None);
+}
+
+#[test]
+fn test_lenient_deserialize() {
+    let tagged = TaggedBase64::new("KEY", b"public key bits").unwrap();
+    let bare = TaggedBase64::encode_raw(b"public key bits");
+
+    // The tagged form is parsed strictly.
+    let json = serde_json::to_string(&tagged).unwrap();
+    let lenient: LenientTaggedBase64 = serde_json::from_str(&json).unwrap();
+    assert_eq!(lenient.0, tagged);
+    assert!(serde_json::from_str::<LenientTaggedBase64>(r#""KEY~AAAA""#).is_err());
+
+    // The bare form gets an empty tag and a fresh checksum.
+    let lenient: LenientTaggedBase64 = serde_json::from_str(&format!("\"{bare}\"")).unwrap();
+    assert_eq!(
+        lenient.0,
+        TaggedBase64::new("", b"public key bits").unwrap()
+    );
+    assert!(lenient.0.is_valid());
+    assert!(serde_json::from_str::<LenientTaggedBase64>(r#""not base64!""#).is_err());
+    // The strict type rejects the bare form.
+    assert!(serde_json::from_str::<TaggedBase64>(&format!("\"{bare}\"")).is_err());
+
+    // Mixed sources in one document.
+    let mixed = format!("[{json}, \"{bare}\"]");
+    let values: Vec<LenientTaggedBase64> = serde_json::from_str(&mixed).unwrap();
+    assert_eq!(values[0].0, tagged);
+    assert_eq!(values[1].0.value(), b"public key bits");
+
+    // Serialization is always the tagged form, and binary is unchanged.
+    assert_eq!(
+        serde_json::to_string(&LenientTaggedBase64(tagged.clone())).unwrap(),
+        json
+    );
+    let bin = bincode::serialize(&tagged).unwrap();
+    assert_eq!(
+        bincode::serialize(&LenientTaggedBase64(tagged.clone())).unwrap(),
+        bin
+    );
+    assert_eq!(
+        bincode::deserialize::<LenientTaggedBase64>(&bin).unwrap().0,
+        tagged
+    );
+}